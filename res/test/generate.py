@@ -0,0 +1,226 @@
+#!/usr/bin/env python3
+"""writes font.ttf, a tiny variable test font
+
+- 1000 units per em, unlike roboto
+- U+E000 is a square that roboto doesn't have
+- 'A' is a triangle, for testing fallback priority
+- a `wght` axis from 100 to 900, the square and its advance
+  are 400 units wider at 900 than at 100
+- the named instances "Thin" (100) and "Black" (900)
+"""
+
+import struct
+from pathlib import Path
+
+UNITS_PER_EM = 1000
+
+# (advance, contours), the contours are clockwise on curve points
+GLYPHS = [
+    # .notdef
+    (500, [[(50, 0), (50, 700), (450, 700), (450, 0)]]),
+    # U+E000
+    (700, [[(100, 0), (100, 700), (600, 700), (600, 0)]]),
+    # A
+    (700, [[(50, 0), (350, 700), (650, 0)]]),
+]
+CMAP = {0x41: 2, 0xE000: 1}
+
+# glyph index -> [(peak of wght, x delta of each point)], the last
+# 4 points are the phantom points (left, right, top and bottom)
+VARIATIONS = {
+    1: [
+        (1.0, [0, 0, 200, 200, 0, 200, 0, 0]),
+        (-1.0, [0, 0, -200, -200, 0, -200, 0, 0]),
+    ],
+}
+
+NAMES = {
+    1: "fontsdf test",
+    2: "Regular",
+    4: "fontsdf test Regular",
+    6: "fontsdftest-Regular",
+    256: "Weight",
+    257: "Thin",
+    258: "Black",
+}
+INSTANCES = [(257, 100.0), (258, 900.0)]
+
+
+def bounds(contours):
+    xs = [x for contour in contours for x, _ in contour]
+    ys = [y for contour in contours for _, y in contour]
+    return min(xs), min(ys), max(xs), max(ys)
+
+
+def glyf_and_loca():
+    glyf = b""
+    loca = [0]
+    for _, contours in GLYPHS:
+        points = [p for contour in contours for p in contour]
+        data = struct.pack(">h4h", len(contours), *bounds(contours))
+        end = -1
+        for contour in contours:
+            end += len(contour)
+            data += struct.pack(">H", end)
+        data += struct.pack(">H", 0)
+        data += bytes([0x01] * len(points))
+        last = (0, 0)
+        xs, ys = b"", b""
+        for x, y in points:
+            xs += struct.pack(">h", x - last[0])
+            ys += struct.pack(">h", y - last[1])
+            last = (x, y)
+        data += xs + ys
+        data += b"\0" * (-len(data) % 4)
+        glyf += data
+        loca.append(len(glyf))
+    return glyf, struct.pack(f">{len(loca)}I", *loca)
+
+
+def head():
+    xmin, ymin, xmax, ymax = (
+        min(b[i] for b in (bounds(c) for _, c in GLYPHS)) if i < 2
+        else max(b[i] for b in (bounds(c) for _, c in GLYPHS))
+        for i in range(4)
+    )
+    return struct.pack(
+        ">HHiIIHHqq4hHHhhh",
+        1, 0, 0x10000, 0, 0x5F0F3CF5, 0b11, UNITS_PER_EM, 0, 0,
+        xmin, ymin, xmax, ymax, 0, 8, 2, 1, 0,
+    )
+
+
+def hhea():
+    return struct.pack(
+        ">HHhhhHhhhhhh4hhH",
+        1, 0, 800, -200, 0, max(a for a, _ in GLYPHS), 0, 0, 700,
+        1, 0, 0, 0, 0, 0, 0, 0, len(GLYPHS),
+    )
+
+
+def hmtx():
+    return b"".join(struct.pack(">Hh", a, bounds(c)[0]) for a, c in GLYPHS)
+
+
+def maxp():
+    points = max(sum(len(c) for c in contours) for _, contours in GLYPHS)
+    contours = max(len(contours) for _, contours in GLYPHS)
+    return struct.pack(
+        ">I14H", 0x10000, len(GLYPHS), points, contours,
+        0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0,
+    )
+
+
+def cmap():
+    segments = [(c, c, (g - c) % 0x10000) for c, g in sorted(CMAP.items())]
+    segments.append((0xFFFF, 0xFFFF, 1))
+    n = len(segments)
+    search = 2 ** (n.bit_length() - 1)
+    subtable = struct.pack(
+        ">7H", 4, 0, 0, n * 2, search * 2, search.bit_length() - 1, n * 2 - search * 2,
+    )
+    subtable += struct.pack(f">{n}H", *(end for _, end, _ in segments))
+    subtable += struct.pack(">H", 0)
+    subtable += struct.pack(f">{n}H", *(start for start, _, _ in segments))
+    subtable += struct.pack(f">{n}H", *(delta for _, _, delta in segments))
+    subtable += struct.pack(f">{n}H", *([0] * n))
+    subtable = subtable[:2] + struct.pack(">H", len(subtable)) + subtable[4:]
+    return struct.pack(">HHHHI", 0, 1, 3, 1, 12) + subtable
+
+
+def name():
+    records, strings = b"", b""
+    for name_id, value in sorted(NAMES.items()):
+        encoded = value.encode("utf-16-be")
+        records += struct.pack(">6H", 3, 1, 0x409, name_id, len(encoded), len(strings))
+        strings += encoded
+    return struct.pack(">3H", 0, len(NAMES), 6 + len(records)) + records + strings
+
+
+def post():
+    return struct.pack(">IIhhIIIII", 0x30000, 0, -100, 50, 0, 0, 0, 0, 0)
+
+
+def fvar():
+    axis = struct.pack(">4s3iHH", b"wght", 100 << 16, 400 << 16, 900 << 16, 0, 256)
+    instances = b"".join(
+        struct.pack(">HHi", name_id, 0, int(value) << 16) for name_id, value in INSTANCES
+    )
+    return struct.pack(">HHHHHHHH", 1, 0, 16, 2, 1, 20, len(INSTANCES), 8) + axis + instances
+
+
+def packed_deltas(deltas):
+    data = b""
+    for i in range(0, len(deltas), 64):
+        run = deltas[i : i + 64]
+        data += bytes([0x40 | (len(run) - 1)]) + struct.pack(f">{len(run)}h", *run)
+    return data
+
+
+def gvar():
+    data = b""
+    offsets = [0]
+    for glyph in range(len(GLYPHS)):
+        tuples = VARIATIONS.get(glyph, [])
+        if tuples:
+            headers = b""
+            serialized = b"\0"  # shared point numbers, all points
+            for peak, dx in tuples:
+                deltas = packed_deltas(dx) + packed_deltas([0] * len(dx))
+                headers += struct.pack(">HHh", len(deltas), 0x8000, int(peak * 16384))
+                serialized += deltas
+            header = struct.pack(">HH", 0x8000 | len(tuples), 4 + len(headers))
+            glyph_data = header + headers + serialized
+            data += glyph_data + b"\0" * (-len(glyph_data) % 4)
+        offsets.append(len(data))
+
+    header_size = 20 + 4 * len(offsets)
+    return (
+        struct.pack(">HHHHIHHI", 1, 0, 1, 0, header_size, len(GLYPHS), 1, header_size)
+        + struct.pack(f">{len(offsets)}I", *offsets)
+        + data
+    )
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(f">{len(data) // 4}I", data)) & 0xFFFFFFFF
+
+
+def font():
+    glyf, loca = glyf_and_loca()
+    tables = {
+        b"cmap": cmap(),
+        b"fvar": fvar(),
+        b"glyf": glyf,
+        b"gvar": gvar(),
+        b"head": head(),
+        b"hhea": hhea(),
+        b"hmtx": hmtx(),
+        b"loca": loca,
+        b"maxp": maxp(),
+        b"name": name(),
+        b"post": post(),
+    }
+
+    n = len(tables)
+    search = 2 ** (n.bit_length() - 1)
+    directory = struct.pack(
+        ">IHHHH", 0x10000, n, search * 16, search.bit_length() - 1, n * 16 - search * 16
+    )
+    offset = 12 + 16 * n
+    body = b""
+    for tag, data in sorted(tables.items()):
+        directory += struct.pack(">4sIII", tag, checksum(data), offset + len(body), len(data))
+        body += data + b"\0" * (-len(data) % 4)
+
+    font = bytearray(directory + body)
+    head_offset = font.index(b"head")
+    head_start = struct.unpack(">I", font[head_offset + 8 : head_offset + 12])[0]
+    adjustment = (0xB1B0AFBA - checksum(bytes(font))) & 0xFFFFFFFF
+    font[head_start + 8 : head_start + 12] = struct.pack(">I", adjustment)
+    return bytes(font)
+
+
+if __name__ == "__main__":
+    Path(__file__).with_name("font.ttf").write_bytes(font())
@@ -0,0 +1,548 @@
+use crate::{Font, Metrics, SdfSettings};
+use alloc::{vec, vec::Vec};
+use glam::Vec2;
+use hashbrown::HashMap;
+
+//
+
+/// sdf glyphs packed into one or more same sized pages
+#[derive(Debug, Clone)]
+pub struct Atlas {
+    px: f32,
+    settings: AtlasSettings,
+    pages: Vec<AtlasPage>,
+    glyphs: HashMap<u16, AtlasGlyph>,
+    chars: HashMap<char, u16>,
+}
+
+/// an atlas that rasterizes glyphs on demand, adds pages up to
+/// [`AtlasSettings::max_pages`] and then evicts the least recently
+/// used glyphs when it runs out of space
+#[derive(Debug, Clone)]
+pub struct DynamicAtlas {
+    px: f32,
+    settings: AtlasSettings,
+    pages: Vec<AtlasPage>,
+    packers: Vec<ShelfPacker>,
+    /// glyphs and when they were last used
+    glyphs: HashMap<u16, (AtlasGlyph, u64)>,
+    tick: u64,
+    dirty: Vec<(usize, AtlasRect)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasSettings {
+    /// page width in pixels
+    pub width: usize,
+    /// page height in pixels
+    pub height: usize,
+    /// empty pixels between the glyphs
+    pub padding: usize,
+    /// how many pages a [`DynamicAtlas`] can have,
+    /// an [`Atlas`] adds as many as it needs
+    pub max_pages: usize,
+    pub sdf: SdfSettings,
+}
+
+/// a single grayscale page of an [`Atlas`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtlasPage {
+    pub width: usize,
+    pub height: usize,
+    /// rows from top to bottom
+    pub image: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasGlyph {
+    pub index: u16,
+    /// index of the page the glyph is in,
+    /// also the layer in [`Atlas::texture_array`]
+    pub page: usize,
+    /// the glyph bitmap position in the page in pixels
+    pub rect: AtlasRect,
+    /// top left corner as texture coordinates
+    pub uv_min: Vec2,
+    /// bottom right corner as texture coordinates
+    pub uv_max: Vec2,
+    /// same as [`Font::metrics_indexed_sdf`]
+    pub metrics: Metrics,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct AtlasRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// shelf packer, each shelf keeps a sorted list of its free spans
+#[derive(Debug, Clone)]
+struct ShelfPacker {
+    width: usize,
+    height: usize,
+    shelves: Vec<Shelf>,
+    /// top of the unused space below the shelves
+    next_y: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Shelf {
+    y: usize,
+    height: usize,
+    /// `(x, width)`
+    free: Vec<(usize, usize)>,
+}
+
+//
+
+impl Atlas {
+    /// rasterize and pack `chars` at `px` sized sdf:s
+    ///
+    /// # Panics
+    ///
+    /// if a glyph doesn't fit in a page
+    pub fn new(
+        font: &Font,
+        px: f32,
+        chars: impl IntoIterator<Item = char>,
+        settings: &AtlasSettings,
+    ) -> Self {
+        let chars: HashMap<char, u16> = chars
+            .into_iter()
+            .map(|c| (c, font.lookup_glyph_index(c)))
+            .collect();
+        let mut atlas = Self::from_indices(font, px, chars.values().copied(), settings);
+        atlas.chars = chars;
+        atlas
+    }
+
+    /// rasterize and pack glyphs at `px` sized sdf:s
+    ///
+    /// # Panics
+    ///
+    /// if a glyph doesn't fit in a page
+    pub fn from_indices(
+        font: &Font,
+        px: f32,
+        indices: impl IntoIterator<Item = u16>,
+        settings: &AtlasSettings,
+    ) -> Self {
+        let mut glyphs: Vec<(u16, Metrics)> = indices
+            .into_iter()
+            .map(|index| {
+                (
+                    index,
+                    font.metrics_indexed_sdf_with(index, px, &settings.sdf),
+                )
+            })
+            .collect();
+
+        // tallest first packs the shelves tighter
+        glyphs.sort_unstable_by_key(|(index, _)| *index);
+        glyphs.dedup_by_key(|(index, _)| *index);
+        glyphs.sort_by(|(_, a), (_, b)| b.height.cmp(&a.height).then(b.width.cmp(&a.width)));
+
+        let mut atlas = Self {
+            px,
+            settings: *settings,
+            pages: Vec::new(),
+            glyphs: HashMap::new(),
+            chars: HashMap::new(),
+        };
+        let mut packers: Vec<ShelfPacker> = Vec::new();
+
+        for (index, metrics) in glyphs {
+            let (page, rect) = if metrics.width == 0 || metrics.height == 0 {
+                (0, AtlasRect::default())
+            } else {
+                let w = metrics.width + settings.padding;
+                let h = metrics.height + settings.padding;
+
+                let found = packers
+                    .iter_mut()
+                    .enumerate()
+                    .find_map(|(page, packer)| Some((page, packer.alloc(w, h)?)));
+                let (page, (x, y)) = match found {
+                    Some(found) => found,
+                    None => {
+                        let mut packer = ShelfPacker::new(settings.width, settings.height);
+                        let pos = packer
+                            .alloc(w, h)
+                            .expect("glyph doesn't fit in an atlas page");
+                        packers.push(packer);
+                        atlas
+                            .pages
+                            .push(AtlasPage::new(settings.width, settings.height));
+                        (packers.len() - 1, pos)
+                    }
+                };
+
+                let rect = AtlasRect {
+                    x,
+                    y,
+                    width: metrics.width,
+                    height: metrics.height,
+                };
+                atlas.pages[page].rasterize(font, index, px, rect, &settings.sdf);
+                (page, rect)
+            };
+
+            let glyph = AtlasGlyph::new(
+                index,
+                page,
+                rect,
+                metrics,
+                (settings.width, settings.height),
+            );
+            atlas.glyphs.insert(index, glyph);
+        }
+
+        atlas
+    }
+
+    pub fn px(&self) -> f32 {
+        self.px
+    }
+
+    pub fn settings(&self) -> &AtlasSettings {
+        &self.settings
+    }
+
+    /// pixels between the output values `0.0` and `1.0`,
+    /// the `pxRange` of msdfgen
+    pub fn distance_range(&self, font: &Font) -> f32 {
+        let sdf = &self.settings.sdf;
+        sdf.spread.pixels(self.px, font.scale_factor(self.px)) / sdf.spread_value
+    }
+
+    pub fn pages(&self) -> &[AtlasPage] {
+        &self.pages
+    }
+
+    /// all pages back to back, for uploading as a texture array
+    pub fn texture_array(&self) -> Vec<u8> {
+        texture_array(&self.pages)
+    }
+
+    /// only works for the chars given to [`Atlas::new`]
+    pub fn glyph(&self, character: char) -> Option<&AtlasGlyph> {
+        self.glyph_indexed(*self.chars.get(&character)?)
+    }
+
+    pub fn glyph_indexed(&self, index: u16) -> Option<&AtlasGlyph> {
+        self.glyphs.get(&index)
+    }
+
+    pub fn glyphs(&self) -> impl Iterator<Item = &AtlasGlyph> {
+        self.glyphs.values()
+    }
+
+    pub fn chars(&self) -> &HashMap<char, u16> {
+        &self.chars
+    }
+}
+
+impl DynamicAtlas {
+    /// an empty atlas for `px` sized sdf:s
+    pub fn new(px: f32, settings: &AtlasSettings) -> Self {
+        Self {
+            px,
+            settings: *settings,
+            pages: Vec::new(),
+            packers: Vec::new(),
+            glyphs: HashMap::new(),
+            tick: 0,
+            dirty: Vec::new(),
+        }
+    }
+
+    pub fn px(&self) -> f32 {
+        self.px
+    }
+
+    pub fn settings(&self) -> &AtlasSettings {
+        &self.settings
+    }
+
+    pub fn pages(&self) -> &[AtlasPage] {
+        &self.pages
+    }
+
+    /// all pages back to back, for uploading as a texture array
+    pub fn texture_array(&self) -> Vec<u8> {
+        texture_array(&self.pages)
+    }
+
+    pub fn glyph(&mut self, font: &Font, character: char) -> Option<AtlasGlyph> {
+        self.glyph_indexed(font, font.lookup_glyph_index(character))
+    }
+
+    /// the glyph from the atlas, rasterized and packed if it is missing
+    ///
+    /// least recently used glyphs are evicted to make space, so glyphs
+    /// returned earlier can be overwritten, `None` if the glyph doesn't
+    /// fit in a page at all
+    ///
+    /// the same `font` has to be used every time
+    pub fn glyph_indexed(&mut self, font: &Font, index: u16) -> Option<AtlasGlyph> {
+        self.tick += 1;
+        if let Some((glyph, last_used)) = self.glyphs.get_mut(&index) {
+            *last_used = self.tick;
+            return Some(*glyph);
+        }
+
+        let metrics = font.metrics_indexed_sdf_with(index, self.px, &self.settings.sdf);
+        let (page, rect) = if metrics.width == 0 || metrics.height == 0 {
+            (0, AtlasRect::default())
+        } else {
+            let w = metrics.width + self.settings.padding;
+            let h = metrics.height + self.settings.padding;
+            let (page, (x, y)) = self.alloc(w, h)?;
+
+            // clear the padding from the old glyphs
+            let used = AtlasRect {
+                x,
+                y,
+                width: w.min(self.settings.width - x),
+                height: h.min(self.settings.height - y),
+            };
+            self.pages[page].clear(used);
+            self.dirty.push((page, used));
+
+            let rect = AtlasRect {
+                x,
+                y,
+                width: metrics.width,
+                height: metrics.height,
+            };
+            self.pages[page].rasterize(font, index, self.px, rect, &self.settings.sdf);
+            (page, rect)
+        };
+
+        let glyph = AtlasGlyph::new(
+            index,
+            page,
+            rect,
+            metrics,
+            (self.settings.width, self.settings.height),
+        );
+        self.glyphs.insert(index, (glyph, self.tick));
+        Some(glyph)
+    }
+
+    /// the glyph if it is already in the atlas, doesn't count as a use
+    pub fn get(&self, index: u16) -> Option<&AtlasGlyph> {
+        self.glyphs.get(&index).map(|(glyph, _)| glyph)
+    }
+
+    pub fn glyphs(&self) -> impl Iterator<Item = &AtlasGlyph> {
+        self.glyphs.values().map(|(glyph, _)| glyph)
+    }
+
+    /// page indices and areas that changed since the last call,
+    /// for partial texture uploads
+    pub fn take_dirty(&mut self) -> Vec<(usize, AtlasRect)> {
+        core::mem::take(&mut self.dirty)
+    }
+
+    /// add pages or evict glyphs until there is space
+    fn alloc(&mut self, w: usize, h: usize) -> Option<(usize, (usize, usize))> {
+        if w > self.settings.width || h > self.settings.height || self.settings.max_pages == 0 {
+            return None;
+        }
+
+        loop {
+            let found = self
+                .packers
+                .iter_mut()
+                .enumerate()
+                .find_map(|(page, packer)| Some((page, packer.alloc(w, h)?)));
+            if found.is_some() {
+                return found;
+            }
+
+            if self.pages.len() < self.settings.max_pages {
+                self.pages
+                    .push(AtlasPage::new(self.settings.width, self.settings.height));
+                self.packers
+                    .push(ShelfPacker::new(self.settings.width, self.settings.height));
+                continue;
+            }
+
+            let (&lru, _) = self
+                .glyphs
+                .iter()
+                .filter(|(_, (glyph, _))| glyph.rect.width != 0)
+                .min_by_key(|(_, (_, last_used))| *last_used)?;
+            let (glyph, _) = self.glyphs.remove(&lru).unwrap();
+            self.packers[glyph.page].free(
+                glyph.rect.x,
+                glyph.rect.y,
+                glyph.rect.width + self.settings.padding,
+            );
+        }
+    }
+}
+
+impl Default for AtlasSettings {
+    fn default() -> Self {
+        Self {
+            width: 1024,
+            height: 1024,
+            padding: 1,
+            max_pages: 1,
+            sdf: SdfSettings::default(),
+        }
+    }
+}
+
+impl AtlasPage {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            image: vec![0; width * height],
+        }
+    }
+
+    fn clear(&mut self, rect: AtlasRect) {
+        for y in rect.y..rect.y + rect.height {
+            let row = y * self.width + rect.x;
+            self.image[row..row + rect.width].fill(0);
+        }
+    }
+
+    fn rasterize(&mut self, font: &Font, index: u16, px: f32, rect: AtlasRect, sdf: &SdfSettings) {
+        let offset = rect.y * self.width + rect.x;
+        font.rasterize_indexed_sdf_into_with(index, px, &mut self.image[offset..], self.width, sdf);
+    }
+}
+
+impl AtlasGlyph {
+    /// `page_size` is the width and height of the pages
+    pub(crate) fn new(
+        index: u16,
+        page: usize,
+        rect: AtlasRect,
+        metrics: Metrics,
+        page_size: (usize, usize),
+    ) -> Self {
+        let size = Vec2::new(page_size.0 as f32, page_size.1 as f32);
+        Self {
+            index,
+            page,
+            rect,
+            uv_min: Vec2::new(rect.x as f32, rect.y as f32) / size,
+            uv_max: Vec2::new((rect.x + rect.width) as f32, (rect.y + rect.height) as f32) / size,
+            metrics,
+        }
+    }
+}
+
+impl ShelfPacker {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            shelves: Vec::new(),
+            next_y: 0,
+        }
+    }
+
+    /// top left corner of a free `w` by `h` area
+    fn alloc(&mut self, w: usize, h: usize) -> Option<(usize, usize)> {
+        if w > self.width || h > self.height {
+            return None;
+        }
+
+        // the shortest shelf that is tall enough and has space
+        let best = self
+            .shelves
+            .iter()
+            .enumerate()
+            .filter(|(_, shelf)| shelf.height >= h)
+            .filter_map(|(i, shelf)| Some((i, shelf.find(w)?, shelf.height)))
+            .min_by_key(|(_, _, height)| *height);
+
+        if let Some((i, span, _)) = best {
+            let shelf = &mut self.shelves[i];
+            return Some((shelf.take(span, w), shelf.y));
+        }
+
+        if self.next_y + h > self.height {
+            return None;
+        }
+
+        let mut shelf = Shelf {
+            y: self.next_y,
+            height: h,
+            free: vec![(0, self.width)],
+        };
+        self.next_y += h;
+        let x = shelf.take(0, w);
+        let y = shelf.y;
+        self.shelves.push(shelf);
+        Some((x, y))
+    }
+
+    /// give back an area from [`ShelfPacker::alloc`]
+    fn free(&mut self, x: usize, y: usize, w: usize) {
+        let Some(shelf) = self.shelves.iter_mut().find(|shelf| shelf.y == y) else {
+            return;
+        };
+        shelf.give_back(x, w);
+
+        // empty shelves at the bottom can be reused with any height
+        while self
+            .shelves
+            .last()
+            .is_some_and(|shelf| shelf.free == [(0, self.width)])
+        {
+            self.next_y = self.shelves.pop().unwrap().y;
+        }
+    }
+}
+
+impl Shelf {
+    /// index of the first free span with room for `w`
+    fn find(&self, w: usize) -> Option<usize> {
+        self.free.iter().position(|&(_, width)| width >= w)
+    }
+
+    fn take(&mut self, span: usize, w: usize) -> usize {
+        let (x, width) = self.free[span];
+        if width == w {
+            self.free.remove(span);
+        } else {
+            self.free[span] = (x + w, width - w);
+        }
+        x
+    }
+    /// insert a free span and merge it with its neighbours
+    fn give_back(&mut self, x: usize, w: usize) {
+        let i = self.free.partition_point(|&(free_x, _)| free_x < x);
+        self.free.insert(i, (x, w));
+
+        if let Some(&(next_x, next_w)) = self.free.get(i + 1)
+            && x + w == next_x
+        {
+            self.free[i].1 += next_w;
+            self.free.remove(i + 1);
+        }
+        if i > 0 {
+            let (prev_x, prev_w) = self.free[i - 1];
+            if prev_x + prev_w == x {
+                self.free[i - 1].1 += self.free[i].1;
+                self.free.remove(i);
+            }
+        }
+    }
+}
+
+//
+
+fn texture_array(pages: &[AtlasPage]) -> Vec<u8> {
+    pages.iter().flat_map(|page| &page.image).copied().collect()
+}
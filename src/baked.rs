@@ -0,0 +1,285 @@
+//! a versioned binary container for baked atlases,
+//! written by [`crate::export::binary`]
+//!
+//! everything is little endian:
+//!
+//! | offset | type         | content                                                   |
+//! |-------:|:-------------|:----------------------------------------------------------|
+//! | 0      | `[u8; 4]`    | [`MAGIC`]                                                 |
+//! | 4      | `u16`        | [`VERSION`]                                               |
+//! | 6      | `u16`        | flags, `1` = has a char table, `2` = has line metrics     |
+//! | 8      | `f32`        | font size in pixels                                       |
+//! | 12     | `f32`        | distance range in pixels, see [`crate::Atlas::distance_range`] |
+//! | 16     | `f32`        | edge value, see [`crate::SdfSettings::edge_value`]        |
+//! | 20     | `[f32; 4]`   | ascent, descent, line gap and new line size in pixels     |
+//! | 36     | `[u32; 3]`   | page width, page height and padding                       |
+//! | 48     | `[u32; 4]`   | page, glyph, char and kerning pair counts                 |
+//! | 64     |              | glyph table sorted by index, [`GLYPH_SIZE`] bytes each    |
+//! |        |              | char table sorted by char, [`CHAR_SIZE`] bytes each       |
+//! |        |              | kerning table sorted by pair, [`KERNING_SIZE`] bytes each |
+//! |        |              | pages, `width * height` bytes each                        |
+//!
+//! glyph: `u16` index, `u16` page, `[u32; 4]` x, y, width and height in the page,
+//! `[i32; 2]` xmin and ymin, `[f32; 2]` advance width and height,
+//! `[f32; 4]` outline bounds xmin, ymin, width and height
+//!
+//! char: `u32` char, `u16` glyph index, `u16` zero
+//!
+//! kerning: `u16` left and `u16` right glyph index, `f32` kerning in pixels
+
+use crate::{
+    FontSdfError, LineMetrics, Metrics, OutlineBounds, atlas::AtlasGlyph, atlas::AtlasRect,
+};
+use core::cmp::Ordering;
+
+//
+
+pub const MAGIC: [u8; 4] = *b"FSDF";
+pub const VERSION: u16 = 1;
+
+pub const HEADER_SIZE: usize = 64;
+pub const GLYPH_SIZE: usize = 52;
+pub const CHAR_SIZE: usize = 8;
+pub const KERNING_SIZE: usize = 8;
+
+pub(crate) const FLAG_CHARS: u16 = 1;
+pub(crate) const FLAG_LINE_METRICS: u16 = 2;
+
+/// a baked atlas read straight from the bytes,
+/// the glyphs and pages are not copied
+#[derive(Debug, Clone, Copy)]
+pub struct BakedAtlas<'a> {
+    px: f32,
+    distance_range: f32,
+    edge_value: f32,
+    line_metrics: Option<LineMetrics>,
+    page_width: usize,
+    page_height: usize,
+    padding: usize,
+    page_count: usize,
+
+    glyphs: &'a [u8],
+    chars: &'a [u8],
+    kerning: &'a [u8],
+    pages: &'a [u8],
+}
+
+//
+
+impl<'a> BakedAtlas<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, FontSdfError> {
+        if bytes.len() < HEADER_SIZE || bytes[..4] != MAGIC {
+            return Err(FontSdfError::BakedAtlas("not a baked fontsdf atlas"));
+        }
+        if read_u16(bytes, 4) != VERSION {
+            return Err(FontSdfError::BakedAtlas("unsupported version"));
+        }
+
+        let flags = read_u16(bytes, 6);
+        let line_metrics = (flags & FLAG_LINE_METRICS != 0).then(|| LineMetrics {
+            ascent: read_f32(bytes, 20),
+            descent: read_f32(bytes, 24),
+            line_gap: read_f32(bytes, 28),
+            new_line_size: read_f32(bytes, 32),
+        });
+
+        let page_width = read_u32(bytes, 36) as usize;
+        let page_height = read_u32(bytes, 40) as usize;
+        let page_count = read_u32(bytes, 48) as usize;
+        let glyph_count = read_u32(bytes, 52) as usize;
+        let char_count = read_u32(bytes, 56) as usize;
+        let kerning_count = read_u32(bytes, 60) as usize;
+
+        let mut rest = &bytes[HEADER_SIZE..];
+        let mut take = |len: Option<usize>| {
+            let len = len
+                .filter(|len| *len <= rest.len())
+                .ok_or(FontSdfError::BakedAtlas("truncated"))?;
+            let (table, tail) = rest.split_at(len);
+            rest = tail;
+            Ok::<_, FontSdfError>(table)
+        };
+        let glyphs = take(glyph_count.checked_mul(GLYPH_SIZE))?;
+        let chars = take(char_count.checked_mul(CHAR_SIZE))?;
+        let kerning = take(kerning_count.checked_mul(KERNING_SIZE))?;
+        let pages = take(
+            page_width
+                .checked_mul(page_height)
+                .and_then(|size| size.checked_mul(page_count)),
+        )?;
+
+        Ok(Self {
+            px: read_f32(bytes, 8),
+            distance_range: read_f32(bytes, 12),
+            edge_value: read_f32(bytes, 16),
+            line_metrics,
+            page_width,
+            page_height,
+            padding: read_u32(bytes, 44) as usize,
+            page_count,
+            glyphs,
+            chars,
+            kerning,
+            pages,
+        })
+    }
+
+    pub fn px(&self) -> f32 {
+        self.px
+    }
+
+    /// pixels between the output values `0.0` and `1.0`
+    pub fn distance_range(&self) -> f32 {
+        self.distance_range
+    }
+
+    /// output value of the glyph edges
+    pub fn edge_value(&self) -> f32 {
+        self.edge_value
+    }
+
+    /// in pixels
+    pub fn line_metrics(&self) -> Option<LineMetrics> {
+        self.line_metrics
+    }
+
+    pub fn page_width(&self) -> usize {
+        self.page_width
+    }
+
+    pub fn page_height(&self) -> usize {
+        self.page_height
+    }
+
+    /// empty pixels between the glyphs
+    pub fn padding(&self) -> usize {
+        self.padding
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.page_count
+    }
+
+    /// grayscale rows from top to bottom
+    pub fn page(&self, page: usize) -> Option<&'a [u8]> {
+        if page >= self.page_count {
+            return None;
+        }
+        // can't overflow, the pages were checked to fit in the data
+        let size = self.page_width * self.page_height;
+        self.pages.get(page * size..(page + 1) * size)
+    }
+
+    /// all pages back to back, for uploading as a texture array
+    pub fn texture_array(&self) -> &'a [u8] {
+        self.pages
+    }
+
+    pub fn glyph(&self, character: char) -> Option<AtlasGlyph> {
+        let record = search(self.chars, CHAR_SIZE, |record| {
+            read_u32(record, 0).cmp(&(character as u32))
+        })?;
+        self.glyph_indexed(read_u16(record, 4))
+    }
+
+    pub fn glyph_indexed(&self, index: u16) -> Option<AtlasGlyph> {
+        let record = search(self.glyphs, GLYPH_SIZE, |record| {
+            read_u16(record, 0).cmp(&index)
+        })?;
+        Some(self.read_glyph(record))
+    }
+
+    pub fn glyphs(&self) -> impl Iterator<Item = AtlasGlyph> + 'a {
+        let this = *self;
+        self.glyphs
+            .chunks_exact(GLYPH_SIZE)
+            .map(move |record| this.read_glyph(record))
+    }
+
+    /// the chars and their glyph indices, empty if
+    /// the atlas was made from glyph indices
+    pub fn chars(&self) -> impl Iterator<Item = (char, u16)> + 'a {
+        self.chars
+            .chunks_exact(CHAR_SIZE)
+            .filter_map(|record| Some((char::from_u32(read_u32(record, 0))?, read_u16(record, 4))))
+    }
+
+    /// in pixels
+    pub fn kerning(&self, left: char, right: char) -> Option<f32> {
+        let left = self.glyph(left)?.index;
+        let right = self.glyph(right)?.index;
+        self.kerning_indexed(left, right)
+    }
+
+    /// in pixels
+    pub fn kerning_indexed(&self, left: u16, right: u16) -> Option<f32> {
+        let record = search(self.kerning, KERNING_SIZE, |record| {
+            (read_u16(record, 0), read_u16(record, 2)).cmp(&(left, right))
+        })?;
+        Some(read_f32(record, 4))
+    }
+
+    fn read_glyph(&self, record: &[u8]) -> AtlasGlyph {
+        let rect = AtlasRect {
+            x: read_u32(record, 4) as usize,
+            y: read_u32(record, 8) as usize,
+            width: read_u32(record, 12) as usize,
+            height: read_u32(record, 16) as usize,
+        };
+        let metrics = Metrics {
+            xmin: read_u32(record, 20) as i32,
+            ymin: read_u32(record, 24) as i32,
+            width: rect.width,
+            height: rect.height,
+            advance_width: read_f32(record, 28),
+            advance_height: read_f32(record, 32),
+            bounds: OutlineBounds {
+                xmin: read_f32(record, 36),
+                ymin: read_f32(record, 40),
+                width: read_f32(record, 44),
+                height: read_f32(record, 48),
+            },
+        };
+        AtlasGlyph::new(
+            read_u16(record, 0),
+            read_u16(record, 2) as usize,
+            rect,
+            metrics,
+            (self.page_width, self.page_height),
+        )
+    }
+}
+
+//
+
+/// binary search over the fixed size records of a sorted table
+fn search(table: &[u8], size: usize, cmp: impl Fn(&[u8]) -> Ordering) -> Option<&[u8]> {
+    let (mut lo, mut hi) = (0, table.len() / size);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let record = &table[mid * size..(mid + 1) * size];
+        match cmp(record) {
+            Ordering::Less => lo = mid + 1,
+            Ordering::Greater => hi = mid,
+            Ordering::Equal => return Some(record),
+        }
+    }
+    None
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+fn read_f32(bytes: &[u8], offset: usize) -> f32 {
+    f32::from_bits(read_u32(bytes, offset))
+}
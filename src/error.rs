@@ -0,0 +1,42 @@
+use core::fmt;
+use ttf_parser::Tag;
+
+//
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontSdfError {
+    /// the font data couldn't be parsed
+    Parse(&'static str),
+    /// the font collection doesn't have a face at this index
+    FaceIndex(u32),
+    /// the font doesn't have a glyph at this index
+    GlyphIndex(u16),
+    /// the glyph is only a bitmap, an svg image or layers of other glyphs
+    UnsupportedOutline(u16),
+    /// the font doesn't have this variation axis or isn't variable
+    UnknownAxis(Tag),
+    /// the data isn't a valid [`crate::baked::BakedAtlas`]
+    BakedAtlas(&'static str),
+    /// the image to rasterize into is too small for the glyph
+    Destination(&'static str),
+}
+
+//
+
+impl fmt::Display for FontSdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontSdfError::Parse(err) => write!(f, "font parsing failed: {err}"),
+            FontSdfError::FaceIndex(index) => write!(f, "no face at index {index}"),
+            FontSdfError::GlyphIndex(index) => write!(f, "no glyph at index {index}"),
+            FontSdfError::UnsupportedOutline(index) => {
+                write!(f, "glyph {index} has no supported outline")
+            }
+            FontSdfError::UnknownAxis(tag) => write!(f, "no variation axis {tag}"),
+            FontSdfError::BakedAtlas(err) => write!(f, "invalid baked atlas: {err}"),
+            FontSdfError::Destination(err) => write!(f, "invalid destination: {err}"),
+        }
+    }
+}
+
+impl core::error::Error for FontSdfError {}
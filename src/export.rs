@@ -0,0 +1,339 @@
+use crate::{
+    Font,
+    atlas::{Atlas, AtlasGlyph},
+    baked,
+};
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
+
+//
+
+/// a char (if the atlas has them) and its glyph
+type Entry<'a> = (Option<char>, &'a AtlasGlyph);
+
+//
+
+/// the json layout of [msdf-atlas-gen](https://github.com/Chlumsky/msdf-atlas-gen)
+/// with `"yOrigin": "bottom"`, all distances are in ems
+///
+/// glyphs are identified by `"unicode"` if the atlas was made
+/// from chars and by `"index"` otherwise, glyphs also get
+/// a non-standard `"page"` if the atlas has more than one page
+///
+/// `font` has to be the one the atlas was made from
+pub fn msdf_atlas_gen_json(atlas: &Atlas, font: &Font) -> String {
+    let mut json = String::new();
+    // writing into a String never fails
+    _ = write_json(&mut json, atlas, font);
+    json
+}
+
+/// the text format of [AngelCode BMFont](https://www.angelcode.com/products/bmfont/doc/file_format.html)
+///
+/// char ids are unicode code points if the atlas was made from chars
+/// and glyph indices otherwise, `page_file` names the image of each page
+///
+/// `font` has to be the one the atlas was made from
+pub fn bmfont(
+    atlas: &Atlas,
+    font: &Font,
+    face: &str,
+    page_file: impl Fn(usize) -> String,
+) -> String {
+    let mut fnt = String::new();
+    // writing into a String never fails
+    _ = write_bmfont(&mut fnt, atlas, font, face, page_file);
+    fnt
+}
+
+/// the binary container described in [`crate::baked`],
+/// read back with [`crate::baked::BakedAtlas`]
+///
+/// `font` has to be the one the atlas was made from
+pub fn binary(atlas: &Atlas, font: &Font) -> Vec<u8> {
+    let px = atlas.px();
+    let settings = atlas.settings();
+    let line = font.horizontal_line_metrics(px);
+
+    let mut glyphs: Vec<Entry> = atlas.glyphs().map(|glyph| (None, glyph)).collect();
+    glyphs.sort_by_key(|(_, glyph)| glyph.index);
+    let mut chars: Vec<(char, u16)> = atlas.chars().iter().map(|(c, i)| (*c, *i)).collect();
+    chars.sort_unstable();
+    let kerning = kerning_pairs(&glyphs, font, px);
+
+    let mut flags = 0;
+    if !chars.is_empty() {
+        flags |= baked::FLAG_CHARS;
+    }
+    if line.is_some() {
+        flags |= baked::FLAG_LINE_METRICS;
+    }
+    let line = line.map_or([0.0; 4], |line| {
+        [line.ascent, line.descent, line.line_gap, line.new_line_size]
+    });
+
+    let mut bin = Vec::new();
+    bin.extend_from_slice(&baked::MAGIC);
+    bin.extend_from_slice(&baked::VERSION.to_le_bytes());
+    bin.extend_from_slice(&flags.to_le_bytes());
+    for f in [px, atlas.distance_range(font), settings.sdf.edge_value]
+        .into_iter()
+        .chain(line)
+    {
+        bin.extend_from_slice(&f.to_le_bytes());
+    }
+    for u in [
+        settings.width,
+        settings.height,
+        settings.padding,
+        atlas.pages().len(),
+        glyphs.len(),
+        chars.len(),
+        kerning.len(),
+    ] {
+        bin.extend_from_slice(&(u as u32).to_le_bytes());
+    }
+    debug_assert_eq!(bin.len(), baked::HEADER_SIZE);
+
+    for (_, glyph) in &glyphs {
+        let m = &glyph.metrics;
+        let r = &glyph.rect;
+        bin.extend_from_slice(&glyph.index.to_le_bytes());
+        bin.extend_from_slice(&(glyph.page as u16).to_le_bytes());
+        for u in [r.x, r.y, r.width, r.height] {
+            bin.extend_from_slice(&(u as u32).to_le_bytes());
+        }
+        bin.extend_from_slice(&m.xmin.to_le_bytes());
+        bin.extend_from_slice(&m.ymin.to_le_bytes());
+        for f in [
+            m.advance_width,
+            m.advance_height,
+            m.bounds.xmin,
+            m.bounds.ymin,
+            m.bounds.width,
+            m.bounds.height,
+        ] {
+            bin.extend_from_slice(&f.to_le_bytes());
+        }
+    }
+    for (c, index) in chars {
+        bin.extend_from_slice(&(c as u32).to_le_bytes());
+        bin.extend_from_slice(&index.to_le_bytes());
+        bin.extend_from_slice(&[0; 2]);
+    }
+    for ((_, left), (_, right), kern) in kerning {
+        bin.extend_from_slice(&left.index.to_le_bytes());
+        bin.extend_from_slice(&right.index.to_le_bytes());
+        bin.extend_from_slice(&kern.to_le_bytes());
+    }
+    for page in atlas.pages() {
+        bin.extend_from_slice(&page.image);
+    }
+
+    bin
+}
+
+//
+
+fn write_bmfont(
+    fnt: &mut String,
+    atlas: &Atlas,
+    font: &Font,
+    face: &str,
+    page_file: impl Fn(usize) -> String,
+) -> core::fmt::Result {
+    let px = atlas.px();
+    let settings = atlas.settings();
+    let glyphs = sorted_glyphs(atlas);
+    let unicode = !atlas.chars().is_empty();
+    let id = |c: &Option<char>, glyph: &AtlasGlyph| c.map_or(glyph.index as u32, |c| c as u32);
+
+    let (line_height, base) = font
+        .horizontal_line_metrics(px)
+        .map_or((px, px), |line| (line.new_line_size, line.ascent));
+    let line_height = line_height.round() as i32;
+    let base = base.round() as i32;
+
+    writeln!(
+        fnt,
+        r#"info face="{}" size={} bold=0 italic=0 charset="" unicode={} stretchH=100 smooth=1 aa=1 padding=0,0,0,0 spacing={},{} outline=0"#,
+        face.replace('"', ""),
+        px.round() as i32,
+        unicode as u8,
+        settings.padding,
+        settings.padding,
+    )?;
+    writeln!(
+        fnt,
+        "common lineHeight={line_height} base={base} scaleW={} scaleH={} pages={} packed=0 alphaChnl=0 redChnl=0 greenChnl=0 blueChnl=0",
+        settings.width,
+        settings.height,
+        atlas.pages().len(),
+    )?;
+    for page in 0..atlas.pages().len() {
+        writeln!(
+            fnt,
+            r#"page id={page} file="{}""#,
+            page_file(page).replace('"', "")
+        )?;
+    }
+
+    writeln!(fnt, "chars count={}", glyphs.len())?;
+    for (c, glyph) in &glyphs {
+        let m = &glyph.metrics;
+        let r = &glyph.rect;
+        writeln!(
+            fnt,
+            "char id={} x={} y={} width={} height={} xoffset={} yoffset={} xadvance={} page={} chnl=15",
+            id(c, glyph),
+            r.x,
+            r.y,
+            r.width,
+            r.height,
+            m.xmin,
+            base - (m.ymin + m.height as i32),
+            m.advance_width.round() as i32,
+            glyph.page,
+        )?;
+    }
+
+    let kernings: Vec<_> = kerning_pairs(&glyphs, font, px)
+        .into_iter()
+        .map(|((lc, l), (rc, r), kern)| (id(lc, l), id(rc, r), kern.round() as i32))
+        .filter(|(_, _, amount)| *amount != 0)
+        .collect();
+    if !kernings.is_empty() {
+        writeln!(fnt, "kernings count={}", kernings.len())?;
+        for (first, second, amount) in kernings {
+            writeln!(fnt, "kerning first={first} second={second} amount={amount}")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_json(json: &mut String, atlas: &Atlas, font: &Font) -> core::fmt::Result {
+    let px = atlas.px();
+    let settings = atlas.settings();
+    let range = atlas.distance_range(font);
+    let multi_page = atlas.pages().len() > 1;
+    let height = settings.height as f32;
+
+    write!(
+        json,
+        r#"{{"atlas":{{"type":"sdf","distanceRange":{range},"distanceRangeMiddle":{},"size":{px},"width":{},"height":{},"yOrigin":"bottom"}}"#,
+        (0.5 - settings.sdf.edge_value) * range,
+        settings.width,
+        settings.height,
+    )?;
+
+    if let Some(line) = font.horizontal_line_metrics(px) {
+        write!(
+            json,
+            r#","metrics":{{"emSize":1,"lineHeight":{},"ascender":{},"descender":{}}}"#,
+            line.new_line_size / px,
+            line.ascent / px,
+            line.descent / px,
+        )?;
+    }
+
+    let glyphs = sorted_glyphs(atlas);
+
+    json.push_str(r#","glyphs":["#);
+    for (i, (character, glyph)) in glyphs.iter().enumerate() {
+        if i != 0 {
+            json.push(',');
+        }
+        match character {
+            Some(c) => write!(json, r#"{{"unicode":{}"#, *c as u32)?,
+            None => write!(json, r#"{{"index":{}"#, glyph.index)?,
+        }
+        let m = &glyph.metrics;
+        write!(json, r#","advance":{}"#, m.advance_width / px)?;
+        if m.width != 0 && m.height != 0 {
+            let r = &glyph.rect;
+            let b = font.sdf_bounds_indexed_with(glyph.index, px, &settings.sdf);
+            write!(
+                json,
+                r#","planeBounds":{{"left":{},"bottom":{},"right":{},"top":{}}}"#,
+                b.xmin / px,
+                b.ymin / px,
+                (b.xmin + b.width) / px,
+                (b.ymin + b.height) / px,
+            )?;
+            write!(
+                json,
+                r#","atlasBounds":{{"left":{},"bottom":{},"right":{},"top":{}}}"#,
+                r.x,
+                height - (r.y + r.height) as f32,
+                r.x + r.width,
+                height - r.y as f32,
+            )?;
+            if multi_page {
+                write!(json, r#","page":{}"#, glyph.page)?;
+            }
+        }
+        json.push('}');
+    }
+    json.push(']');
+
+    json.push_str(r#","kerning":["#);
+    for (i, ((left_char, left), (right_char, right), kern)) in
+        kerning_pairs(&glyphs, font, px).into_iter().enumerate()
+    {
+        if i != 0 {
+            json.push(',');
+        }
+        match (left_char, right_char) {
+            (Some(l), Some(r)) => write!(
+                json,
+                r#"{{"unicode1":{},"unicode2":{}"#,
+                *l as u32, *r as u32
+            )?,
+            _ => write!(
+                json,
+                r#"{{"index1":{},"index2":{}"#,
+                left.index, right.index
+            )?,
+        }
+        write!(json, r#","advance":{}}}"#, kern / px)?;
+    }
+    json.push_str("]}");
+
+    Ok(())
+}
+
+/// chars (if any) and their glyphs in a stable order
+fn sorted_glyphs(atlas: &Atlas) -> Vec<Entry<'_>> {
+    let mut glyphs: Vec<_> = if atlas.chars().is_empty() {
+        atlas.glyphs().map(|glyph| (None, glyph)).collect()
+    } else {
+        atlas
+            .chars()
+            .iter()
+            .filter_map(|(c, index)| Some((Some(*c), atlas.glyph_indexed(*index)?)))
+            .collect()
+    };
+    glyphs.sort_by_key(|(c, glyph)| (*c, glyph.index));
+    glyphs
+}
+
+/// all glyph pairs with non-zero kerning in pixels
+fn kerning_pairs<'a>(
+    glyphs: &'a [Entry<'a>],
+    font: &'a Font,
+    px: f32,
+) -> Vec<(&'a Entry<'a>, &'a Entry<'a>, f32)> {
+    // the pairs are mostly not in the kerning cache, so the face is parsed once for all of them
+    let face = font.face();
+    glyphs
+        .iter()
+        .flat_map(|left| glyphs.iter().map(move |right| (left, right)))
+        .filter_map(|(left, right)| {
+            let kern = font
+                .horizontal_kern_indexed_in(&face, left.1.index, right.1.index, px)
+                .filter(|kern| *kern != 0.0)?;
+            Some((left, right, kern))
+        })
+        .collect()
+}
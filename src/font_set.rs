@@ -0,0 +1,145 @@
+use crate::{Font, LineMetrics, Metrics, OutlineBounds, SdfSettings, Spread};
+use alloc::{vec, vec::Vec};
+
+//
+
+/// a primary font and its fallbacks, each char
+/// comes from the first font that has it
+///
+/// `px` is the em size in every font, so glyphs from fonts with
+/// different units per em still line up, [`Spread::FontUnits`]
+/// is in the units of the primary font
+#[derive(Debug, Clone)]
+pub struct FontSet {
+    fonts: Vec<Font>,
+}
+
+//
+
+impl FontSet {
+    pub fn new(primary: Font) -> Self {
+        Self {
+            fonts: vec![primary],
+        }
+    }
+
+    /// add a fallback after the previous ones
+    pub fn push(&mut self, fallback: Font) {
+        self.fonts.push(fallback);
+    }
+
+    /// [`Self::push`] as a builder
+    pub fn with(mut self, fallback: Font) -> Self {
+        self.push(fallback);
+        self
+    }
+
+    /// the primary font first
+    pub fn fonts(&self) -> &[Font] {
+        &self.fonts
+    }
+
+    pub fn font(&self, font: usize) -> Option<&Font> {
+        self.fonts.get(font)
+    }
+
+    /// the font and glyph index of the char, missing chars
+    /// are the `.notdef` glyph (`0`) of the primary font
+    pub fn lookup(&self, character: char) -> (usize, u16) {
+        self.fonts
+            .iter()
+            .enumerate()
+            .find_map(|(font, f)| {
+                let index = f.lookup_glyph_index(character);
+                (index != 0).then_some((font, index))
+            })
+            .unwrap_or((0, 0))
+    }
+
+    /// the settings as given to the fonts,
+    /// with the spread converted for the fallbacks
+    pub fn settings_for(&self, font: usize, settings: &SdfSettings) -> SdfSettings {
+        let mut settings = *settings;
+        if font != 0
+            && let Spread::FontUnits(units) = settings.spread
+        {
+            settings.spread = Spread::Em(units * self.fonts[0].scale_factor(1.0));
+        }
+        settings
+    }
+
+    pub fn metrics_sdf(&self, character: char, px: f32) -> Metrics {
+        self.metrics_sdf_with(character, px, &SdfSettings::default())
+    }
+
+    pub fn metrics_sdf_with(&self, character: char, px: f32, settings: &SdfSettings) -> Metrics {
+        let (font, index) = self.lookup(character);
+        let settings = self.settings_for(font, settings);
+        self.fonts[font].metrics_indexed_sdf_with(index, px, &settings)
+    }
+
+    pub fn sdf_bounds(&self, character: char, px: f32) -> OutlineBounds {
+        self.sdf_bounds_with(character, px, &SdfSettings::default())
+    }
+
+    pub fn sdf_bounds_with(
+        &self,
+        character: char,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> OutlineBounds {
+        let (font, index) = self.lookup(character);
+        let settings = self.settings_for(font, settings);
+        self.fonts[font].sdf_bounds_indexed_with(index, px, &settings)
+    }
+
+    pub fn rasterize_sdf(&self, character: char, px: f32) -> (Metrics, Vec<u8>) {
+        self.rasterize_sdf_with(character, px, &SdfSettings::default())
+    }
+
+    pub fn rasterize_sdf_with(
+        &self,
+        character: char,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> (Metrics, Vec<u8>) {
+        let (font, index) = self.lookup(character);
+        let settings = self.settings_for(font, settings);
+        self.fonts[font].rasterize_indexed_sdf_with(index, px, &settings)
+    }
+
+    /// only between chars from the same font
+    pub fn horizontal_kern(&self, left: char, right: char, px: f32) -> Option<f32> {
+        let (left_font, left) = self.lookup(left);
+        let (right_font, right) = self.lookup(right);
+        if left_font != right_font {
+            return None;
+        }
+        self.fonts[left_font].horizontal_kern_indexed(left, right, px)
+    }
+
+    pub fn horizontal_advance(&self, character: char, px: f32) -> f32 {
+        let (font, index) = self.lookup(character);
+        self.fonts[font].horizontal_advance_indexed(index, px)
+    }
+
+    /// the tallest ascent, the deepest descent and the
+    /// largest line gap of all fonts, so that no glyph overlaps
+    /// the next line
+    pub fn horizontal_line_metrics(&self, px: f32) -> Option<LineMetrics> {
+        self.fonts
+            .iter()
+            .filter_map(|font| font.horizontal_line_metrics(px))
+            .reduce(|a, b| {
+                let ascent = a.ascent.max(b.ascent);
+                let descent = a.descent.min(b.descent);
+                let line_gap = a.line_gap.max(b.line_gap);
+                LineMetrics {
+                    ascent,
+                    descent,
+                    line_gap,
+                    new_line_size: ascent - descent + line_gap,
+                }
+            })
+    }
+}
@@ -1,8 +1,5 @@
-use crate::{
-    CURVE_RESOLUTION,
-    math::{BoundingBox, Curve, Line, Quad, Ray, Segment},
-};
-use alloc::vec::Vec;
+use crate::math::{BoundingBox, CURVE_SAMPLES, Curve, Edge, Line, Quad, Ray, Segment};
+use alloc::{vec, vec::Vec};
 use glam::{BVec4A, Vec2, Vec4};
 use ttf_parser::OutlineBuilder;
 
@@ -12,7 +9,11 @@ use ttf_parser::OutlineBuilder;
 pub struct Geometry {
     current: Vec2,
     min_x: f32,
+    edge_sum: f32,
     contours: Vec<Contour>,
+    /// points along the curves, for a quick upper bound of the distance,
+    /// the end of each curve is the start of the next edge
+    samples: Vec<Vec2>,
 
     current_contour: Contour,
     current_contour_edge_sum: f32,
@@ -23,7 +24,9 @@ pub struct Geometry {
 #[derive(Debug, Clone, Default)]
 pub struct Contour {
     pub aabb: BoundingBox,
-    pub lines: Vec<Line>,
+    pub edges: Vec<Edge>,
+    /// msdf edge color of each edge in `edges`
+    pub colors: Vec<EdgeColor>,
     pub mode: ContourMode,
 }
 
@@ -34,53 +37,253 @@ pub enum ContourMode {
     Subtractive,
 }
 
+/// the contours of a [`Geometry`] flattened into lines, used for the inside test
+#[derive(Debug, Clone, Default)]
+pub struct Flattened {
+    min_x: f32,
+    contours: Vec<FlatContour>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FlatContour {
+    pub aabb: BoundingBox,
+    pub lines: Vec<Line>,
+}
+
+/// msdf channels that an edge contributes to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EdgeColor(u8);
+
 //
 
+impl EdgeColor {
+    pub const BLACK: Self = Self(0b000);
+    pub const RED: Self = Self(0b001);
+    pub const GREEN: Self = Self(0b010);
+    pub const YELLOW: Self = Self(0b011);
+    pub const BLUE: Self = Self(0b100);
+    pub const MAGENTA: Self = Self(0b101);
+    pub const CYAN: Self = Self(0b110);
+    pub const WHITE: Self = Self(0b111);
+
+    pub fn has(self, channel: Self) -> bool {
+        self.0 & channel.0 != 0
+    }
+
+    /// next color in the cyan -> magenta -> yellow cycle
+    fn switch(self) -> Self {
+        if self == Self::BLACK || self == Self::WHITE {
+            return Self::CYAN;
+        }
+        let shifted = self.0 << 1;
+        Self((shifted | shifted >> 3) & Self::WHITE.0)
+    }
+
+    /// like [`Self::switch`] but never shares two channels with `banned`
+    fn switch_banned(self, banned: Self) -> Self {
+        let combined = Self(self.0 & banned.0);
+        if combined == Self::RED || combined == Self::GREEN || combined == Self::BLUE {
+            Self(combined.0 ^ Self::WHITE.0)
+        } else {
+            self.switch()
+        }
+    }
+}
+
 impl Geometry {
     pub fn new() -> Self {
         Self::default()
     }
 
-    /// check if the point is 'inside' this character
-    /// by drawing a line to left and for each entry:
-    ///  - increase counter by 1 if entering a contour
-    ///  - decrease counter by 1 if exiting a contour
-    ///
-    /// if counter is greater than zero,
-    /// then the point was inside the character
-    pub fn is_inside(&self, point: (Vec4, Vec4)) -> BVec4A {
-        let half = Vec4::ONE * 0.5;
+    pub fn iter_edges(&self) -> impl Iterator<Item = Edge> + '_ {
+        self.iter_parts()
+            .flat_map(|shape| shape.edges.iter())
+            .copied()
+    }
 
-        let from = (half + point.0.round(), half + point.1.round());
+    pub fn iter_parts(&self) -> impl Iterator<Item = &'_ Contour> + '_ {
+        self.contours.iter()
+    }
 
-        let ray = Ray {
-            from_x: from.0,
-            from_y: from.1,
-            to_x: half + Vec4::splat(self.min_x - 100.0),
-            to_y: from.1,
-        };
+    /// flatten the edges so that none of the
+    /// lines are further than `tolerance` from the curves
+    pub fn flatten(&self, tolerance: f32) -> Flattened {
+        let contours = self
+            .contours
+            .iter()
+            .map(|contour| {
+                let mut lines = Vec::new();
+                for edge in contour.edges.iter() {
+                    edge.flatten_into(tolerance, &mut lines);
+                }
+                FlatContour {
+                    aabb: contour.aabb,
+                    lines,
+                }
+            })
+            .collect();
+        Flattened {
+            min_x: self.min_x,
+            contours,
+        }
+    }
+
+    /// exact squared distance to the closest edge
+    pub fn distance_squared(&self, point: (Vec4, Vec4)) -> Vec4 {
+        // the lines are cheap and exact, and the samples on the curves give
+        // a close upper bound, so that most curves don't need to be solved
+        let mut best = self
+            .samples
+            .iter()
+            .map(|sample| {
+                let x = point.0 - Vec4::splat(sample.x);
+                let y = point.1 - Vec4::splat(sample.y);
+                x * x + y * y
+            })
+            .fold(Vec4::INFINITY, Vec4::min);
+        for edge in self.iter_edges() {
+            if let Edge::Line(line) = edge {
+                best = best.min(line.distance_ord(point));
+            }
+        }
 
-        let mut hit_counts = Vec4::ZERO;
         for contour in self.contours.iter() {
-            hit_counts += ray.hit_count(contour);
+            if !contour.aabb.min_distance_squared(point).cmplt(best).any() {
+                continue;
+            }
+            for edge in contour.edges.iter() {
+                if let Edge::Line(_) = edge {
+                    continue;
+                }
+                if edge.aabb().min_distance_squared(point).cmplt(best).any() {
+                    best = best.min(edge.distance_ord(point));
+                }
+            }
         }
-        hit_counts.cmpgt(Vec4::ZERO)
+        best
     }
 
-    pub fn iter_lines(&self) -> impl Iterator<Item = Line> + '_ {
-        self.iter_parts()
-            .flat_map(|shape| shape.lines.iter())
-            .copied()
+    /// signed distances (positive inside) to the closest edge of each msdf channel
+    pub fn msdf_distance(&self, point: (Vec4, Vec4)) -> [Vec4; 3] {
+        self.channel_distance(
+            point,
+            [EdgeColor::RED, EdgeColor::GREEN, EdgeColor::BLUE],
+            self.contours
+                .iter()
+                .map(|contour| contour.colors.as_slice()),
+        )
     }
 
-    pub fn iter_parts(&self) -> impl Iterator<Item = &'_ Contour> + '_ {
-        self.contours.iter()
+    /// edge colors for a dual distance field, one list per contour
+    ///
+    /// the two channels only switch at convex corners,
+    /// the glyph is the intersection (min) of the two channels
+    pub fn dual_colors(&self) -> Vec<Vec<EdgeColor>> {
+        self.contours
+            .iter()
+            .map(|contour| contour.dual_colors(self.orientation()))
+            .collect()
+    }
+
+    /// signed distances (positive inside) to the closest edge of both dual
+    /// distance field channels
+    ///
+    /// `colors` should come from [`Self::dual_colors`]
+    pub fn dual_distance(&self, point: (Vec4, Vec4), colors: &[Vec<EdgeColor>]) -> [Vec4; 2] {
+        self.channel_distance(
+            point,
+            [EdgeColor::RED, EdgeColor::GREEN],
+            colors.iter().map(Vec::as_slice),
+        )
+    }
+
+    fn channel_distance<'a, const N: usize>(
+        &self,
+        point: (Vec4, Vec4),
+        channels: [EdgeColor; N],
+        colors: impl Iterator<Item = &'a [EdgeColor]>,
+    ) -> [Vec4; N] {
+        let orientation = self.orientation();
+
+        let mut best_distance_squared = [Vec4::INFINITY; N];
+        let mut best_orthogonality = [Vec4::ZERO; N];
+        let mut best_value = [Vec4::splat(-f32::MAX); N];
+
+        for (contour, colors) in self.contours.iter().zip(colors) {
+            let n = contour.edges.len();
+            for (i, (edge, &color)) in contour.edges.iter().zip(colors.iter()).enumerate() {
+                let bound = edge.aabb().min_distance_squared(point);
+                let could_be_closer = channels
+                    .iter()
+                    .zip(best_distance_squared.iter())
+                    .any(|(channel, best)| color.has(*channel) && bound.cmple(*best * 1.001).any());
+                if !could_be_closer {
+                    continue;
+                }
+
+                let (distance_squared, t, perpendicular) = edge.pseudo_distance(point);
+                if distance_squared.is_nan_mask().any() {
+                    continue;
+                }
+                let distance = distance_squared.powf(0.5);
+                let orthogonality = perpendicular.abs() / distance.max(Vec4::splat(f32::EPSILON));
+                let sign = perpendicular.signum() * orientation;
+
+                let prev = colors[(i + n - 1) % n];
+                let next = colors[(i + 1) % n];
+
+                for (channel, _) in channels
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, channel)| color.has(**channel))
+                {
+                    let best = best_distance_squared[channel];
+                    let epsilon = best * 1e-6;
+                    // the first candidate is always taken, on an edge the tie break
+                    // below can't pick it because the orthogonality is zero
+                    let closer = best.cmpeq(Vec4::INFINITY)
+                        | (distance_squared - best).cmplt(-epsilon)
+                        | ((distance_squared - best).abs().cmple(epsilon)
+                            & orthogonality.cmpgt(best_orthogonality[channel]));
+
+                    // the ends of an edge are extended to infinity if the neighbouring
+                    // edge is not in this channel, which is what keeps the corners sharp
+                    let extend_start = BVec4A::splat(!prev.has(channels[channel]));
+                    let extend_end = BVec4A::splat(!next.has(channels[channel]));
+                    let extend =
+                        (t.cmplt(Vec4::ZERO) & extend_start) | (t.cmpgt(Vec4::ONE) & extend_end);
+                    let value = Vec4::select(extend, perpendicular.abs(), distance) * sign;
+
+                    best_distance_squared[channel] = Vec4::select(closer, distance_squared, best);
+                    best_orthogonality[channel] =
+                        Vec4::select(closer, orthogonality, best_orthogonality[channel]);
+                    best_value[channel] = Vec4::select(closer, value, best_value[channel]);
+                }
+            }
+        }
+
+        best_value
+    }
+
+    /// edges are clockwise around the filled area in TrueType fonts,
+    /// so the inside is on the right side (-1.0) of each edge
+    fn orientation(&self) -> f32 {
+        if self.edge_sum >= 0.0 { -1.0 } else { 1.0 }
     }
 
     pub fn add_shape(&mut self, shape: impl Segment + Copy) {
-        let iter = shape.iter_lines(CURVE_RESOLUTION);
-        self.current_contour.lines.reserve(iter.len());
-        self.current_contour.lines.extend(iter);
+        // zero length edges have no direction, which would break the corner detection
+        let mut control_points = shape.control_points();
+        let first = control_points.next();
+        if control_points.any(|p| Some(p) != first) {
+            let edge = shape.into();
+            if !matches!(edge, Edge::Line(_)) {
+                self.samples.extend(
+                    (0..CURVE_SAMPLES).map(|i| edge.point(i as f32 / CURVE_SAMPLES as f32)),
+                );
+            }
+            self.current_contour.edges.push(edge);
+        }
         self.current_contour.aabb = self.current_contour.aabb.union(shape.aabb());
 
         let mut iter = shape.control_points();
@@ -109,6 +312,8 @@ impl Geometry {
         } else {
             ContourMode::Subtractive
         };
+        self.current_contour.color_edges();
+        self.edge_sum += self.current_contour_edge_sum;
         self.current_contour_edge_sum = 0.0;
         self.current_contour_first_control_point = None;
         self.prev_control_point = Vec2::ZERO;
@@ -117,6 +322,158 @@ impl Geometry {
     }
 }
 
+impl Flattened {
+    /// check if the point is 'inside' this character
+    /// by drawing a line to left and for each entry:
+    ///  - increase counter by 1 if entering a contour
+    ///  - decrease counter by 1 if exiting a contour
+    ///
+    /// if counter is greater than zero,
+    /// then the point was inside the character
+    pub fn is_inside(&self, point: (Vec4, Vec4)) -> BVec4A {
+        let half = Vec4::ONE * 0.5;
+
+        let from = (half + point.0.round(), half + point.1.round());
+
+        let ray = Ray {
+            from_x: from.0,
+            from_y: from.1,
+            to_x: half + Vec4::splat(self.min_x - 100.0),
+            to_y: from.1,
+        };
+
+        let mut hit_counts = Vec4::ZERO;
+        for contour in self.contours.iter() {
+            hit_counts += ray.hit_count(contour);
+        }
+        hit_counts.cmpgt(Vec4::ZERO)
+    }
+
+    pub fn iter_lines(&self) -> impl Iterator<Item = Line> + '_ {
+        self.contours
+            .iter()
+            .flat_map(|contour| contour.lines.iter())
+            .copied()
+    }
+}
+
+impl Contour {
+    /// assign msdf edge colors so that the edges meeting
+    /// at a sharp corner never share more than one channel
+    ///
+    /// this is the simple edge coloring strategy from msdfgen
+    fn color_edges(&mut self) {
+        let n = self.edges.len();
+        let corners: Vec<usize> = (0..n).filter(|&i| self.corner_turn(i).is_some()).collect();
+
+        // a teardrop needs at least 3 edges to color
+        if let &[corner] = corners.as_slice()
+            && n < 3
+        {
+            self.edges = self
+                .edges
+                .iter()
+                .flat_map(|edge| edge.split_in_thirds())
+                .collect();
+            self.colors = Vec::new();
+            self.color_edges_teardrop(corner * 3);
+            return;
+        }
+
+        self.colors.clear();
+        self.colors.resize(n, EdgeColor::WHITE);
+
+        match corners.as_slice() {
+            // smooth contour, every channel sees every edge
+            [] => {}
+            &[corner] => self.color_edges_teardrop(corner),
+            corners => {
+                let initial = EdgeColor::WHITE.switch();
+                let mut color = initial;
+                let mut spline = 0;
+                for i in 0..n {
+                    let index = (corners[0] + i) % n;
+                    if spline + 1 < corners.len() && corners[spline + 1] == index {
+                        spline += 1;
+                        let banned = if spline == corners.len() - 1 {
+                            initial
+                        } else {
+                            EdgeColor::BLACK
+                        };
+                        color = color.switch_banned(banned);
+                    }
+                    self.colors[index] = color;
+                }
+            }
+        }
+    }
+
+    /// split the edges into thirds around the only corner
+    fn color_edges_teardrop(&mut self, corner: usize) {
+        const COLORS: [EdgeColor; 3] = [EdgeColor::CYAN, EdgeColor::WHITE, EdgeColor::MAGENTA];
+
+        let n = self.edges.len();
+        self.colors.clear();
+        self.colors.resize(n, EdgeColor::WHITE);
+        for i in 0..n {
+            let third = (3.0 + 2.875 * i as f32 / (n - 1) as f32 - 1.4375 + 0.5) as usize - 2;
+            self.colors[(corner + i) % n] = COLORS[third];
+        }
+    }
+
+    /// like [`Self::color_edges`] but with only two channels
+    /// that switch at the convex corners
+    fn dual_colors(&self, orientation: f32) -> Vec<EdgeColor> {
+        const COLORS: [EdgeColor; 2] = [EdgeColor::RED, EdgeColor::GREEN];
+
+        let mut colors = vec![EdgeColor::YELLOW; self.edges.len()];
+
+        // a corner is convex if it turns towards the inside
+        let n = self.edges.len();
+        let corners: Vec<usize> = (0..n)
+            .filter(|&i| {
+                self.corner_turn(i)
+                    .is_some_and(|turn| turn * orientation > 0.0)
+            })
+            .collect();
+        // a single corner can't be split between two channels
+        let &[first, _, ..] = corners.as_slice() else {
+            return colors;
+        };
+
+        let mut corner = 0;
+        for i in 0..n {
+            let index = (first + i) % n;
+            if corners.get(corner + 1) == Some(&index) {
+                corner += 1;
+            }
+
+            // with an odd number of corners, the last run would have the same color
+            // as the first one, so it goes to both channels and that corner stays round
+            colors[index] = if corners.len() % 2 == 1 && corner == corners.len() - 1 {
+                EdgeColor::YELLOW
+            } else {
+                COLORS[corner % 2]
+            };
+        }
+
+        colors
+    }
+
+    /// direction of the turn (positive is left) if the edge
+    /// `index` and the one before it form a sharp corner
+    fn corner_turn(&self, index: usize) -> Option<f32> {
+        // sin(3.0), msdfgen's default corner angle threshold
+        const CROSS_THRESHOLD: f32 = 0.141_120_01;
+
+        let n = self.edges.len();
+        let prev = self.edges[(index + n - 1) % n].end_direction();
+        let next = self.edges[index].start_direction();
+        let turn = prev.perp_dot(next);
+        (prev.dot(next) <= 0.0 || turn.abs() > CROSS_THRESHOLD).then_some(turn)
+    }
+}
+
 impl OutlineBuilder for Geometry {
     fn move_to(&mut self, x: f32, y: f32) {
         let to = Vec2::new(x, y).round();
@@ -0,0 +1,124 @@
+use crate::{Font, atlas::Atlas};
+use alloc::vec::Vec;
+use glam::Vec2;
+
+//
+
+/// a glyph placed on the screen, textured from an [`Atlas`] page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphQuad {
+    pub index: u16,
+    /// byte offset of the char in the text
+    pub byte_offset: usize,
+    pub page: usize,
+    /// top left corner in pixels, y grows downwards
+    pub min: Vec2,
+    /// bottom right corner in pixels
+    pub max: Vec2,
+    pub uv_min: Vec2,
+    pub uv_max: Vec2,
+}
+
+//
+
+/// lay out `text` at `px` sized glyphs from an atlas
+/// baked at any size, `(0, 0)` is the top left corner
+/// of the first line
+///
+/// lines are wrapped at whitespace (or anywhere in a word that
+/// doesn't fit on its own) to stay within `max_width` and at `'\n'`,
+/// glyphs missing from the atlas take space but get no quad
+///
+/// `font` has to be the one the atlas was made from
+pub fn layout(
+    font: &Font,
+    atlas: &Atlas,
+    text: &str,
+    px: f32,
+    max_width: Option<f32>,
+) -> Vec<GlyphQuad> {
+    let scale = px / atlas.px();
+    let (ascent, line_height) = font
+        .horizontal_line_metrics(px)
+        .map_or((px, px), |line| (line.ascent, line.new_line_size));
+    let max_width = max_width.unwrap_or(f32::INFINITY);
+
+    let mut quads: Vec<GlyphQuad> = Vec::new();
+    let mut pen = Vec2::new(0.0, ascent);
+    let mut prev = None;
+    // the first quad and pen position after the last whitespace
+    let mut wrap: Option<(usize, f32)> = None;
+
+    for (byte_offset, c) in text.char_indices() {
+        if c == '\n' {
+            pen = Vec2::new(0.0, pen.y + line_height);
+            prev = None;
+            wrap = None;
+            continue;
+        }
+
+        let index = font.lookup_glyph_index(c);
+        let advance = font.horizontal_advance_indexed(index, px);
+        let kern = prev
+            .and_then(|prev| font.horizontal_kern_indexed(prev, index, px))
+            .unwrap_or(0.0);
+        prev = Some(index);
+        pen.x += kern;
+
+        if !c.is_whitespace() && pen.x + advance > max_width {
+            match wrap.take() {
+                // move the current word to the next line
+                Some((first, x)) => {
+                    let offset = Vec2::new(-x, line_height);
+                    for quad in &mut quads[first..] {
+                        quad.min += offset;
+                        quad.max += offset;
+                    }
+                    pen += offset;
+                }
+                // the word doesn't fit on its own
+                None if pen.x - kern > 0.0 => {
+                    pen = Vec2::new(0.0, pen.y + line_height);
+                }
+                None => {}
+            }
+        }
+
+        quads.extend(glyph_quad(font, atlas, index, byte_offset, pen, scale));
+
+        pen.x += advance;
+        if c.is_whitespace() {
+            wrap = Some((quads.len(), pen.x));
+        }
+    }
+
+    quads
+}
+
+//
+
+/// the quad of a glyph with its origin at `pen`
+pub(crate) fn glyph_quad(
+    font: &Font,
+    atlas: &Atlas,
+    index: u16,
+    byte_offset: usize,
+    pen: Vec2,
+    scale: f32,
+) -> Option<GlyphQuad> {
+    let glyph = atlas
+        .glyph_indexed(index)
+        .filter(|glyph| glyph.rect.width != 0 && glyph.rect.height != 0)?;
+    let bounds = font.sdf_bounds_indexed_with(index, atlas.px(), &atlas.settings().sdf);
+    let min = Vec2::new(bounds.xmin, -(bounds.ymin + bounds.height)) * scale;
+    let size = Vec2::new(bounds.width, bounds.height) * scale;
+    Some(GlyphQuad {
+        index,
+        byte_offset,
+        page: glyph.page,
+        min: pen + min,
+        max: pen + min + size,
+        uv_min: glyph.uv_min,
+        uv_max: glyph.uv_max,
+    })
+}
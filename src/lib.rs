@@ -9,94 +9,291 @@ extern crate alloc;
 
 //
 
-use alloc::{vec, vec::Vec};
-use core::num::NonZeroU16;
-use fontdue::FontSettings;
-use geom::Geometry;
+use alloc::{boxed::Box, sync::Arc, vec, vec::Vec};
+use core::{
+    fmt,
+    num::NonZeroU16,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use geom::{Flattened, Geometry};
 use glam::{UVec4, Vec4};
 use hashbrown::HashMap;
 use math::Line;
-use ttf_parser::{Face, Rect};
+use once_cell::race::OnceBox;
+use ttf_parser::{
+    Face, FaceParsingError, GlyphId, Rect,
+    gpos::{PairAdjustment, PositioningSubtable},
+};
 
 //
 
-pub use fontdue::{Metrics, OutlineBounds};
+pub use atlas::{Atlas, AtlasSettings, DynamicAtlas};
+pub use error::FontSdfError;
+pub use font_set::FontSet;
+pub use fontdue::{FontSettings, LineMetrics, Metrics, OutlineBounds};
+pub use half::f16;
+pub use settings::{DistanceUnit, SdfSettings, Spread};
+pub use ttf_parser::{Tag, VariationAxis};
+pub use variation::NamedInstance;
 
 use self::math::bvec4_to_uvec4;
 
 //
 
+pub mod atlas;
+pub mod baked;
+pub mod error;
+pub mod export;
+pub mod font_set;
 pub mod geom;
+pub mod layout;
 pub mod math;
+pub mod mesh;
+pub mod settings;
+#[cfg(feature = "shaping")]
+pub mod shape;
+pub mod variation;
 
-pub const CURVE_RESOLUTION: usize = 8;
+/// how far the flattened curves can be from the real ones
+/// when rasterizing, in output pixels
+pub const CURVE_TOLERANCE: f32 = 0.1;
+
+/// the flattened lines are cached for tolerances of
+/// `2^FLATTEN_MIN_EXPONENT..=2^FLATTEN_MAX_EXPONENT` font units
+const FLATTEN_MIN_EXPONENT: i32 = -6;
+const FLATTEN_MAX_EXPONENT: i32 = 9;
+const FLATTEN_LEVELS: usize = (FLATTEN_MAX_EXPONENT - FLATTEN_MIN_EXPONENT + 1) as usize;
 
 //
 
+/// sdf rasterization on top of a [`fontdue::Font`]
+///
+/// loading a font only reads the cmap and the metrics, the sdf outlines
+/// are read the first time each glyph is used, and [`Self::inner`] is
+/// loaded the first time it is used, which outlines every glyph
 #[derive(Debug, Clone)]
 pub struct Font {
-    glyphs: Vec<(Geometry, Rect)>,
+    /// outlined on the first use
+    glyphs: Vec<OnceBox<Glyph>>,
+    /// horizontal and vertical advances in font units
+    advances: Vec<(f32, f32)>,
     oo_units_per_em: f32,
+    /// from every cmap subtable, the same as fontdue
+    chars: HashMap<char, NonZeroU16>,
+    /// horizontal and vertical line metrics in font units
+    line_metrics: (Option<LineMetrics>, Option<LineMetrics>),
+    /// the only copy of the font data, all the outlines and tables are read from it
+    data: Arc<[u8]>,
+    settings: FontSettings,
+    variations: Vec<(Tag, f32)>,
+    /// kerning in font units of the recently used glyph pairs
+    kerning: KerningCache,
+    /// the GPOS lookups of the kern feature, read on the first use
+    kern_lookups: OnceBox<Vec<u16>>,
+
+    /// only for the coverage rasterization, loaded on the first use
+    inner: OnceBox<fontdue::Font>,
+    /// replaces `inner` after [`Self::inner_mut`]
+    inner_mut: Option<Box<fontdue::Font>>,
+}
 
-    inner: fontdue::Font,
+#[derive(Debug, Clone)]
+struct Glyph {
+    geometry: (Geometry, Rect),
+    /// flattened on the first use at each tolerance level
+    flattened: [OnceBox<Flattened>; FLATTEN_LEVELS],
 }
 
 struct InternalMetrics {
     sf: f32,
     radius: usize,
+    edge_value: f32,
+    /// output value change per font unit
+    value_scale: f32,
     offset_x: f32,
     offset_y: f32,
     width: usize,
     height: usize,
 }
 
+/// a fixed number of slots indexed by a hash of the glyph pair, a pair
+/// replaces whatever was in its slot, each slot holds the filled bit,
+/// `left << 33 | right << 17`, the has kerning bit and the kerning
+#[derive(Default)]
+struct KerningCache {
+    slots: OnceBox<Vec<AtomicU64>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MultiChannel {
+    Msdf,
+    Mtsdf,
+    Dual,
+}
+
 //
 
 impl Font {
+    /// loaded on the first use, which outlines every glyph
+    ///
+    /// only used for the coverage rasterization, [`Self::chars`]
+    /// and the metrics are read from the font data
     pub fn inner(&self) -> &fontdue::Font {
-        &self.inner
+        match &self.inner_mut {
+            Some(inner) => inner,
+            None => self
+                .inner
+                .get_or_init(|| Box::new(load_inner(&self.data, self.settings))),
+        }
     }
 
     pub fn inner_mut(&mut self) -> &mut fontdue::Font {
-        &mut self.inner
+        // a OnceBox can't be borrowed mutably, so the font moves out of it
+        let (inner, data, settings) = (&mut self.inner, &self.data, self.settings);
+        self.inner_mut.get_or_insert_with(|| {
+            let font = match inner.get() {
+                Some(font) => font.clone(),
+                None => load_inner(data, settings),
+            };
+            *inner = OnceBox::new();
+            Box::new(font)
+        })
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<Self, &'static str> {
-        let inner = fontdue::Font::from_bytes(bytes, FontSettings::default())?;
-        let face = Face::parse(bytes, 0).unwrap();
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FontSdfError> {
+        Self::from_bytes_with(bytes, FontSettings::default())
+    }
+
+    /// `settings.collection_index` picks the face from
+    /// a font collection (`.ttc` or `.otc`)
+    pub fn from_bytes_with(bytes: &[u8], settings: FontSettings) -> Result<Self, FontSdfError> {
+        let face_index = settings.collection_index;
+        let face = Face::parse(bytes, face_index).map_err(|err| match err {
+            FaceParsingError::FaceIndexOutOfBounds => FontSdfError::FaceIndex(face_index),
+            _ => FontSdfError::Parse("invalid font data"),
+        })?;
 
         let oo_units_per_em = 1.0 / face.units_per_em() as f32;
 
-        let initial = (
-            Geometry::new(),
-            Rect {
-                x_min: 0,
-                y_min: 0,
-                x_max: 0,
-                y_max: 0,
-            },
-        );
-        let mut glyphs = Vec::new();
-        glyphs.resize(face.number_of_glyphs() as usize, initial);
-        for (&c, &i) in inner.chars().iter() {
-            (|| {
-                let mut geom = Geometry::new();
-                let bb = face.outline_glyph(face.glyph_index(c)?, &mut geom)?;
-                glyphs[i.get() as usize] = (geom, bb);
-                Some(())
-            })();
+        let mut chars = HashMap::new();
+        for subtable in face
+            .tables()
+            .cmap
+            .into_iter()
+            .flat_map(|cmap| cmap.subtables)
+        {
+            subtable.codepoints(|codepoint| {
+                let glyph = subtable.glyph_index(codepoint);
+                if let (Some(c), Some(glyph)) = (
+                    char::from_u32(codepoint),
+                    glyph.and_then(|glyph| NonZeroU16::new(glyph.0)),
+                ) {
+                    chars.insert(c, glyph);
+                }
+            });
+        }
+        // fontdue fails to load these
+        if chars
+            .values()
+            .any(|glyph| glyph.get() >= face.number_of_glyphs())
+        {
+            return Err(FontSdfError::Parse("cmap glyph index out of bounds"));
         }
 
-        Ok(Self {
-            glyphs,
+        let line_metrics = |ascent: i16, descent: i16, line_gap: i16| {
+            let (ascent, descent, line_gap) = (ascent as i32, descent as i32, line_gap as i32);
+            LineMetrics {
+                ascent: ascent as f32,
+                descent: descent as f32,
+                line_gap: line_gap as f32,
+                new_line_size: (ascent - descent + line_gap) as f32,
+            }
+        };
+        let horizontal = line_metrics(face.ascender(), face.descender(), face.line_gap());
+        let vertical = face.vertical_ascender().map(|ascent| {
+            line_metrics(
+                ascent,
+                face.vertical_descender().unwrap_or(0),
+                face.vertical_line_gap().unwrap_or(0),
+            )
+        });
+
+        let mut font = Self {
+            glyphs: Vec::new(),
+            advances: Vec::new(),
             oo_units_per_em,
+            chars,
+            line_metrics: (Some(horizontal), vertical),
+            data: bytes.into(),
+            settings,
+            variations: Vec::new(),
+            kerning: KerningCache::default(),
+            kern_lookups: OnceBox::new(),
 
-            inner,
-        })
+            inner: OnceBox::new(),
+            inner_mut: None,
+        };
+        font.load_glyphs(&face);
+        Ok(font)
+    }
+
+    /// the axes of a variable font
+    pub fn variation_axes(&self) -> Vec<VariationAxis> {
+        self.face().variation_axes().into_iter().collect()
+    }
+
+    /// the predefined instances of a variable font, like "Bold Condensed"
+    pub fn named_instances(&self) -> Vec<NamedInstance> {
+        variation::named_instances(&self.face())
+    }
+
+    pub fn variations(&self) -> &[(Tag, f32)] {
+        &self.variations
+    }
+
+    /// set the variation axis values (like `wght` = 700) of a variable font,
+    /// the outlines and metrics are loaded again for all glyphs,
+    /// axes not in `variations` use their default values
+    ///
+    /// the metrics and coverage rasterization of [`Self::inner`] are
+    /// still from the default instance
+    pub fn set_variations(&mut self, variations: &[(Tag, f32)]) -> Result<(), FontSdfError> {
+        let data = self.data.clone();
+        let mut face = Face::parse(&data, self.settings.collection_index).unwrap();
+        for &(axis, value) in variations {
+            face.set_variation(axis, value)
+                .ok_or(FontSdfError::UnknownAxis(axis))?;
+        }
+
+        self.variations = variations.to_vec();
+        self.load_glyphs(&face);
+        Ok(())
+    }
+
+    /// a copy of the font with other variations, to bake several instances
+    pub fn instance(&self, variations: &[(Tag, f32)]) -> Result<Self, FontSdfError> {
+        let mut font = self.clone();
+        font.set_variations(variations)?;
+        Ok(font)
     }
 
     pub fn chars(&self) -> &HashMap<char, NonZeroU16> {
-        self.inner.chars()
+        &self.chars
+    }
+
+    /// glyph indices go from `0` to `glyph_count() - 1`,
+    /// including glyphs without a char like ligatures and alternates
+    pub fn glyph_count(&self) -> u16 {
+        self.glyphs.len() as u16
+    }
+
+    /// how many glyphs have been outlined for the sdf:s so far,
+    /// each glyph is outlined the first time it is used
+    pub fn outlined_glyph_count(&self) -> usize {
+        self.glyphs
+            .iter()
+            .filter(|glyph| glyph.get().is_some())
+            .count()
     }
 
     pub fn scale_factor(&self, px: f32) -> f32 {
@@ -104,8 +301,13 @@ impl Font {
     }
 
     pub fn radius(&self, px: f32) -> usize {
+        self.radius_with(px, &SdfSettings::default())
+    }
+
+    /// padding around the glyphs in pixels
+    pub fn radius_with(&self, px: f32, settings: &SdfSettings) -> usize {
         let scale_factor = self.scale_factor(px);
-        (255.0 * scale_factor).ceil() as usize + 1
+        settings.spread.pixels(px, scale_factor).ceil() as usize + 1
     }
 
     pub fn metrics_sdf(&self, character: char, px: f32) -> Metrics {
@@ -113,48 +315,332 @@ impl Font {
     }
 
     pub fn metrics_indexed_sdf(&self, index: u16, px: f32) -> Metrics {
-        let (_, bb) = self.glyphs.get(index as usize).unwrap();
-        let metrics = self.internal_metrics(px, bb);
+        self.metrics_indexed_sdf_with(index, px, &SdfSettings::default())
+    }
+
+    pub fn metrics_sdf_with(&self, character: char, px: f32, settings: &SdfSettings) -> Metrics {
+        self.metrics_indexed_sdf_with(self.lookup_glyph_index(character), px, settings)
+    }
+
+    pub fn metrics_indexed_sdf_with(&self, index: u16, px: f32, settings: &SdfSettings) -> Metrics {
+        let (_, bb) = self.geometry_indexed(index);
+        let metrics = self.internal_metrics(px, bb, settings);
         self.modify_metrics(index, px, metrics.radius, metrics.width, metrics.height)
     }
 
+    pub fn sdf_bounds(&self, character: char, px: f32) -> OutlineBounds {
+        self.sdf_bounds_indexed(self.lookup_glyph_index(character), px)
+    }
+
+    /// the exact position of the sdf bitmap edges relative
+    /// to the glyph origin in pixels, [`Metrics::xmin`] and
+    /// [`Metrics::ymin`] are only whole pixel approximations
+    pub fn sdf_bounds_indexed(&self, index: u16, px: f32) -> OutlineBounds {
+        self.sdf_bounds_indexed_with(index, px, &SdfSettings::default())
+    }
+
+    pub fn sdf_bounds_with(
+        &self,
+        character: char,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> OutlineBounds {
+        self.sdf_bounds_indexed_with(self.lookup_glyph_index(character), px, settings)
+    }
+
+    pub fn sdf_bounds_indexed_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> OutlineBounds {
+        let (_, bb) = self.geometry_indexed(index);
+        let metrics = self.internal_metrics(px, bb, settings);
+
+        // pixel centers are at the sample points
+        let radius = metrics.radius as f32 + 0.5;
+        OutlineBounds {
+            xmin: metrics.offset_x - radius,
+            ymin: metrics.offset_y - radius,
+            width: metrics.width as f32,
+            height: metrics.height as f32,
+        }
+    }
+
     pub fn rasterize_sdf(&self, character: char, px: f32) -> (Metrics, Vec<u8>) {
         self.rasterize_indexed_sdf(self.lookup_glyph_index(character), px)
     }
 
+    /// works for any glyph index in the face, not only the ones in [`Self::chars`]
     pub fn rasterize_indexed_sdf(&self, index: u16, px: f32) -> (Metrics, Vec<u8>) {
-        let (geom, bb) = self.geometry_indexed(index);
+        self.rasterize_indexed_sdf_with(index, px, &SdfSettings::default())
+    }
+
+    pub fn rasterize_sdf_with(
+        &self,
+        character: char,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> (Metrics, Vec<u8>) {
+        self.rasterize_indexed_sdf_with(self.lookup_glyph_index(character), px, settings)
+    }
+
+    pub fn rasterize_indexed_sdf_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> (Metrics, Vec<u8>) {
+        let (_, bb) = self.geometry_indexed(index);
+        let metrics = self.internal_metrics(px, bb, settings);
+
+        let mut image = vec![0; metrics.width * metrics.height];
+        let metrics =
+            self.rasterize_indexed_sdf_into_with(index, px, &mut image, metrics.width, settings);
+        (metrics, image)
+    }
+
+    pub fn rasterize_sdf_into(
+        &self,
+        character: char,
+        px: f32,
+        dst: &mut [u8],
+        stride: usize,
+    ) -> Metrics {
+        self.rasterize_indexed_sdf_into(self.lookup_glyph_index(character), px, dst, stride)
+    }
+
+    /// rasterize into a sub-rectangle of an existing image
+    ///
+    /// no image is allocated, the only allocations are the outline and its
+    /// flattened lines, which are cached in the font the first time a glyph
+    /// is used at a similar size
+    ///
+    /// `dst` starts at the top left pixel of the glyph and `stride` is the
+    /// row length of the image, the size can be queried beforehand with
+    /// [`Self::metrics_indexed_sdf`]
+    ///
+    /// # Panics
+    ///
+    /// if `stride` is less than the glyph width or `dst` is too short
+    pub fn rasterize_indexed_sdf_into(
+        &self,
+        index: u16,
+        px: f32,
+        dst: &mut [u8],
+        stride: usize,
+    ) -> Metrics {
+        self.rasterize_indexed_sdf_into_with(index, px, dst, stride, &SdfSettings::default())
+    }
+
+    pub fn rasterize_sdf_into_with(
+        &self,
+        character: char,
+        px: f32,
+        dst: &mut [u8],
+        stride: usize,
+        settings: &SdfSettings,
+    ) -> Metrics {
+        self.rasterize_indexed_sdf_into_with(
+            self.lookup_glyph_index(character),
+            px,
+            dst,
+            stride,
+            settings,
+        )
+    }
+
+    pub fn rasterize_indexed_sdf_into_with(
+        &self,
+        index: u16,
+        px: f32,
+        dst: &mut [u8],
+        stride: usize,
+        settings: &SdfSettings,
+    ) -> Metrics {
+        let (_, bb) = self.geometry_indexed(index);
+        let metrics = self.internal_metrics(px, bb, settings);
+        let w = metrics.width;
+        let h = metrics.height;
+
+        if w != 0 && h != 0 {
+            assert!(stride >= w, "stride is less than the glyph width");
+            assert!(
+                dst.len() >= (h - 1) * stride + w,
+                "destination is too short"
+            );
+        }
+
+        self.sdf_chunks(index, &metrics, |idx, d| {
+            // convert to pixels
+            let distances = metrics.to_u8(d).to_array();
+            // the last chunk can go past the last pixel
+            for (i, distance) in (idx..w * h).zip(distances) {
+                dst[i / w * stride + i % w] = distance as u8;
+            }
+        });
+
+        self.modify_metrics(index, px, metrics.radius, w, h)
+    }
+
+    pub fn rasterize_sdf_f32(
+        &self,
+        character: char,
+        px: f32,
+        unit: DistanceUnit,
+    ) -> (Metrics, Vec<f32>) {
+        self.rasterize_indexed_sdf_f32(self.lookup_glyph_index(character), px, unit)
+    }
+
+    /// the signed distances without any quantization, positive inside the glyph
+    pub fn rasterize_indexed_sdf_f32(
+        &self,
+        index: u16,
+        px: f32,
+        unit: DistanceUnit,
+    ) -> (Metrics, Vec<f32>) {
+        self.rasterize_indexed_sdf_f32_with(index, px, &SdfSettings::default(), unit)
+    }
+
+    pub fn rasterize_sdf_f32_with(
+        &self,
+        character: char,
+        px: f32,
+        settings: &SdfSettings,
+        unit: DistanceUnit,
+    ) -> (Metrics, Vec<f32>) {
+        self.rasterize_indexed_sdf_f32_with(self.lookup_glyph_index(character), px, settings, unit)
+    }
+
+    /// the signed distances without any quantization, positive inside the glyph
+    ///
+    /// only the padding from `settings` is used, not the value mapping
+    pub fn rasterize_indexed_sdf_f32_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+        unit: DistanceUnit,
+    ) -> (Metrics, Vec<f32>) {
+        let (_, bb) = self.geometry_indexed(index);
+        let metrics = self.internal_metrics(px, bb, settings);
+
+        let scale = match unit {
+            DistanceUnit::Pixels => metrics.sf,
+            DistanceUnit::Em => self.oo_units_per_em,
+            DistanceUnit::FontUnits => 1.0,
+        };
+
+        let mut image = vec![0.0; metrics.chunk_count() * 4];
+        self.sdf_chunks(index, &metrics, |idx, d| {
+            image[idx..idx + 4].copy_from_slice(&(d * scale).to_array());
+        });
+        image.truncate(metrics.width * metrics.height);
+
+        (
+            self.modify_metrics(index, px, metrics.radius, metrics.width, metrics.height),
+            image,
+        )
+    }
+
+    pub fn rasterize_sdf_u16(&self, character: char, px: f32) -> (Metrics, Vec<u16>) {
+        self.rasterize_indexed_sdf_u16(self.lookup_glyph_index(character), px)
+    }
+
+    /// same as [`Self::rasterize_indexed_sdf`] but normalized to the full `u16` range
+    pub fn rasterize_indexed_sdf_u16(&self, index: u16, px: f32) -> (Metrics, Vec<u16>) {
+        self.rasterize_indexed_sdf_u16_with(index, px, &SdfSettings::default())
+    }
+
+    pub fn rasterize_sdf_u16_with(
+        &self,
+        character: char,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> (Metrics, Vec<u16>) {
+        self.rasterize_indexed_sdf_u16_with(self.lookup_glyph_index(character), px, settings)
+    }
+
+    pub fn rasterize_indexed_sdf_u16_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> (Metrics, Vec<u16>) {
+        let (_, bb) = self.geometry_indexed(index);
+        let metrics = self.internal_metrics(px, bb, settings);
+
+        let mut image = vec![0; metrics.chunk_count() * 4];
+        self.sdf_chunks(index, &metrics, |idx, d| {
+            let distances = metrics.to_u16(d);
+            image[idx] = distances.x as u16;
+            image[idx + 1] = distances.y as u16;
+            image[idx + 2] = distances.z as u16;
+            image[idx + 3] = distances.w as u16;
+        });
+        image.truncate(metrics.width * metrics.height);
 
-        let metrics = self.internal_metrics(px, bb);
-        let inv_sf = 1.0 / metrics.sf;
+        (
+            self.modify_metrics(index, px, metrics.radius, metrics.width, metrics.height),
+            image,
+        )
+    }
+
+    pub fn rasterize_sdf_f16(&self, character: char, px: f32) -> (Metrics, Vec<f16>) {
+        self.rasterize_indexed_sdf_f16(self.lookup_glyph_index(character), px)
+    }
+
+    /// same as [`Self::rasterize_indexed_sdf`] but as half floats in `0.0..=1.0`
+    pub fn rasterize_indexed_sdf_f16(&self, index: u16, px: f32) -> (Metrics, Vec<f16>) {
+        self.rasterize_indexed_sdf_f16_with(index, px, &SdfSettings::default())
+    }
+
+    pub fn rasterize_sdf_f16_with(
+        &self,
+        character: char,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> (Metrics, Vec<f16>) {
+        self.rasterize_indexed_sdf_f16_with(self.lookup_glyph_index(character), px, settings)
+    }
+
+    pub fn rasterize_indexed_sdf_f16_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> (Metrics, Vec<f16>) {
+        let (_, bb) = self.geometry_indexed(index);
+        let metrics = self.internal_metrics(px, bb, settings);
+
+        let mut image = vec![f16::ZERO; metrics.chunk_count() * 4];
+        self.sdf_chunks(index, &metrics, |idx, d| {
+            let distances = metrics.to_unorm(d).to_array().map(f16::from_f32);
+            image[idx..idx + 4].copy_from_slice(&distances);
+        });
+        image.truncate(metrics.width * metrics.height);
+
+        (
+            self.modify_metrics(index, px, metrics.radius, metrics.width, metrics.height),
+            image,
+        )
+    }
+
+    /// the single channel sdf pixel loop, `write` gets the index
+    /// of the first pixel and the signed distances (in font units)
+    /// of each chunk of 4 pixels
+    fn sdf_chunks(
+        &self,
+        index: u16,
+        metrics: &InternalMetrics,
+        mut write: impl FnMut(usize, Vec4),
+    ) {
+        let (geom, _) = self.geometry_indexed(index);
+        let lines = self.flattened(index, metrics.sf);
 
         // process in chunks of 4
-        let w = metrics.width as u32;
-        let h = metrics.height as u32;
-        let chunk_count = (w * h).div_ceil(4); // divide and round UP, (more if the last chunk doesn't cover everything)
-        let mut image = vec![0; (chunk_count * 4) as usize]; // maybe not zero init as they are not read before written to
-        for (idx, p) in (0..w * h / 4).map(|i| i * 4).map(|i| {
-            (
-                i as usize,
-                (
-                    (UVec4::new(i % w, (i + 1) % w, (i + 2) % w, (i + 3) % w).as_vec4()
-                        - metrics.radius as f32
-                        + metrics.offset_x)
-                        * inv_sf,
-                    (UVec4::new(
-                        h - 1 - i / w,
-                        h - 1 - (i + 1) / w,
-                        h - 1 - (i + 2) / w,
-                        h - 1 - (i + 3) / w,
-                    )
-                    .as_vec4()
-                        - metrics.radius as f32
-                        + metrics.offset_y)
-                        * inv_sf,
-                ),
-            )
-        }) {
-            let is_inside = geom.is_inside(p);
+        for (idx, p) in metrics.pixels() {
+            let is_inside = lines.is_inside(p);
 
             // if false {
             //     image[idx] = is_inside.test(0) as u8 * 255;
@@ -164,26 +650,160 @@ impl Font {
             //     continue;
             // }
 
-            let distance_squared = geom
-                .iter_lines()
-                .map(|s| s.distance_ord(p))
-                .reduce(|acc, next| acc.min(next))
-                .unwrap_or(Vec4::ONE);
+            let distance_squared = geom.distance_squared(p);
 
             // invert pixels that are 'inside' the geometry
             let sign = bvec4_to_uvec4(is_inside).as_vec4() * 2.0 - 1.0;
-            let d = Line::distance_finalize(distance_squared) * 0.5 * sign;
+            let d = Line::distance_finalize(distance_squared) * sign;
 
-            // convert to pixels
-            let distances = (d + Vec4::splat(128.0)).as_uvec4();
-            image[idx] = distances.x as u8;
-            image[idx + 1] = distances.y as u8;
-            image[idx + 2] = distances.z as u8;
-            image[idx + 3] = distances.w as u8;
+            write(idx, d);
+        }
+    }
+
+    pub fn rasterize_msdf(&self, character: char, px: f32) -> (Metrics, Vec<u8>) {
+        self.rasterize_indexed_msdf(self.lookup_glyph_index(character), px)
+    }
+
+    /// multi-channel sdf, 3 bytes (RGB) per pixel
+    ///
+    /// the distance is the median of the three channels,
+    /// which keeps sharp corners sharp at large scales
+    pub fn rasterize_indexed_msdf(&self, index: u16, px: f32) -> (Metrics, Vec<u8>) {
+        self.rasterize_indexed_msdf_with(index, px, &SdfSettings::default())
+    }
+
+    pub fn rasterize_msdf_with(
+        &self,
+        character: char,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> (Metrics, Vec<u8>) {
+        self.rasterize_indexed_msdf_with(self.lookup_glyph_index(character), px, settings)
+    }
+
+    pub fn rasterize_indexed_msdf_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> (Metrics, Vec<u8>) {
+        self.rasterize_indexed_multi_channel(index, px, settings, MultiChannel::Msdf)
+    }
+
+    pub fn rasterize_mtsdf(&self, character: char, px: f32) -> (Metrics, Vec<u8>) {
+        self.rasterize_indexed_mtsdf(self.lookup_glyph_index(character), px)
+    }
+
+    /// msdf with the true signed distance in the alpha channel, 4 bytes (RGBA) per pixel
+    ///
+    /// the alpha channel is the same as [`Self::rasterize_indexed_sdf`],
+    /// which is better for soft effects like glow and shadows
+    pub fn rasterize_indexed_mtsdf(&self, index: u16, px: f32) -> (Metrics, Vec<u8>) {
+        self.rasterize_indexed_mtsdf_with(index, px, &SdfSettings::default())
+    }
+
+    pub fn rasterize_mtsdf_with(
+        &self,
+        character: char,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> (Metrics, Vec<u8>) {
+        self.rasterize_indexed_mtsdf_with(self.lookup_glyph_index(character), px, settings)
+    }
+
+    pub fn rasterize_indexed_mtsdf_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> (Metrics, Vec<u8>) {
+        self.rasterize_indexed_multi_channel(index, px, settings, MultiChannel::Mtsdf)
+    }
+
+    pub fn rasterize_dual_sdf(&self, character: char, px: f32) -> (Metrics, Vec<u8>) {
+        self.rasterize_indexed_dual_sdf(self.lookup_glyph_index(character), px)
+    }
+
+    /// dual distance field, 2 bytes (RG) per pixel
+    ///
+    /// the distance is the minimum of the two channels, each convex
+    /// corner is the intersection of its two edges from separate channels
+    ///
+    /// from: https://steamcdn-a.akamaihd.net/apps/valve/2007/SIGGRAPH2007_AlphaTestedMagnification.pdf
+    pub fn rasterize_indexed_dual_sdf(&self, index: u16, px: f32) -> (Metrics, Vec<u8>) {
+        self.rasterize_indexed_dual_sdf_with(index, px, &SdfSettings::default())
+    }
+
+    pub fn rasterize_dual_sdf_with(
+        &self,
+        character: char,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> (Metrics, Vec<u8>) {
+        self.rasterize_indexed_dual_sdf_with(self.lookup_glyph_index(character), px, settings)
+    }
+
+    pub fn rasterize_indexed_dual_sdf_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> (Metrics, Vec<u8>) {
+        self.rasterize_indexed_multi_channel(index, px, settings, MultiChannel::Dual)
+    }
+
+    fn rasterize_indexed_multi_channel(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+        mode: MultiChannel,
+    ) -> (Metrics, Vec<u8>) {
+        let (geom, bb) = self.geometry_indexed(index);
+
+        let metrics = self.internal_metrics(px, bb, settings);
+        let lines = self.flattened(index, metrics.sf);
+        let dual_colors = (mode == MultiChannel::Dual).then(|| geom.dual_colors());
+        let channels = mode.channels();
+
+        let w = metrics.width;
+        let h = metrics.height;
+        let mut image = vec![0; metrics.chunk_count() * 4 * channels];
+        for (idx, p) in metrics.pixels() {
+            let is_inside = lines.is_inside(p);
+            let [r, g, b] = match &dual_colors {
+                Some(colors) => {
+                    let [r, g] = geom.dual_distance(p, colors);
+                    [r, g, Vec4::ZERO]
+                }
+                None => geom.msdf_distance(p),
+            };
+            let decoded = match mode {
+                MultiChannel::Dual => r.min(g),
+                _ => r.max(g).min(r.min(g).max(b)),
+            };
+
+            // the pseudo-distances can disagree with the actual inside test
+            // near overlapping contours, fall back to the true distance there
+            let wrong_sign = decoded.cmpgt(Vec4::ZERO) ^ is_inside;
+            let d = if mode == MultiChannel::Mtsdf || wrong_sign.any() {
+                let sign = bvec4_to_uvec4(is_inside).as_vec4() * 2.0 - 1.0;
+                Line::distance_finalize(geom.distance_squared(p)) * sign
+            } else {
+                Vec4::ZERO
+            };
+            let [r, g, b] = [r, g, b].map(|c| Vec4::select(wrong_sign, d, c));
+
+            for (i, c) in [r, g, b, d].into_iter().take(channels).enumerate() {
+                let distances = metrics.to_u8(c);
+                image[idx * channels + i] = distances.x as u8;
+                image[(idx + 1) * channels + i] = distances.y as u8;
+                image[(idx + 2) * channels + i] = distances.z as u8;
+                image[(idx + 3) * channels + i] = distances.w as u8;
+            }
         }
 
-        // cut out those extra pixels
-        image.truncate((w * h) as usize);
+        image.truncate(w * h * channels);
 
         (
             self.modify_metrics(index, px, metrics.radius, metrics.width, metrics.height),
@@ -191,12 +811,229 @@ impl Font {
         )
     }
 
+    /// [`Self::geometry_indexed`] but with an error instead of a panic
+    /// if the glyph index is not in the font
+    ///
+    /// the indexed sdf metrics, bounds and every rasterize output have a
+    /// `try_` variant like this, the ones without it panic on out of range
+    /// glyph indices, the functions that take a `char` never panic
+    pub fn try_geometry_indexed(&self, index: u16) -> Result<&'_ (Geometry, Rect), FontSdfError> {
+        self.glyph(index).map(|glyph| &glyph.geometry)
+    }
+
+    pub fn try_metrics_indexed_sdf(&self, index: u16, px: f32) -> Result<Metrics, FontSdfError> {
+        self.try_metrics_indexed_sdf_with(index, px, &SdfSettings::default())
+    }
+
+    pub fn try_metrics_indexed_sdf_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> Result<Metrics, FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.metrics_indexed_sdf_with(index, px, settings))
+    }
+
+    pub fn try_sdf_bounds_indexed(
+        &self,
+        index: u16,
+        px: f32,
+    ) -> Result<OutlineBounds, FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.sdf_bounds_indexed(index, px))
+    }
+
+    pub fn try_sdf_bounds_indexed_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> Result<OutlineBounds, FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.sdf_bounds_indexed_with(index, px, settings))
+    }
+
+    pub fn try_rasterize_indexed_sdf(
+        &self,
+        index: u16,
+        px: f32,
+    ) -> Result<(Metrics, Vec<u8>), FontSdfError> {
+        self.try_rasterize_indexed_sdf_with(index, px, &SdfSettings::default())
+    }
+
+    pub fn try_rasterize_indexed_sdf_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> Result<(Metrics, Vec<u8>), FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.rasterize_indexed_sdf_with(index, px, settings))
+    }
+
+    /// also an error instead of a panic if `dst` is too short
+    /// or `stride` is less than the glyph width
+    pub fn try_rasterize_indexed_sdf_into(
+        &self,
+        index: u16,
+        px: f32,
+        dst: &mut [u8],
+        stride: usize,
+    ) -> Result<Metrics, FontSdfError> {
+        self.try_rasterize_indexed_sdf_into_with(index, px, dst, stride, &SdfSettings::default())
+    }
+
+    pub fn try_rasterize_indexed_sdf_into_with(
+        &self,
+        index: u16,
+        px: f32,
+        dst: &mut [u8],
+        stride: usize,
+        settings: &SdfSettings,
+    ) -> Result<Metrics, FontSdfError> {
+        let metrics = self.try_metrics_indexed_sdf_with(index, px, settings)?;
+        let (w, h) = (metrics.width, metrics.height);
+        if w != 0 && h != 0 {
+            if stride < w {
+                return Err(FontSdfError::Destination(
+                    "stride is less than the glyph width",
+                ));
+            }
+            if dst.len() < (h - 1) * stride + w {
+                return Err(FontSdfError::Destination("destination is too short"));
+            }
+        }
+        Ok(self.rasterize_indexed_sdf_into_with(index, px, dst, stride, settings))
+    }
+
+    pub fn try_rasterize_indexed_sdf_f32(
+        &self,
+        index: u16,
+        px: f32,
+        unit: DistanceUnit,
+    ) -> Result<(Metrics, Vec<f32>), FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.rasterize_indexed_sdf_f32(index, px, unit))
+    }
+
+    pub fn try_rasterize_indexed_sdf_f32_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+        unit: DistanceUnit,
+    ) -> Result<(Metrics, Vec<f32>), FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.rasterize_indexed_sdf_f32_with(index, px, settings, unit))
+    }
+
+    pub fn try_rasterize_indexed_sdf_u16(
+        &self,
+        index: u16,
+        px: f32,
+    ) -> Result<(Metrics, Vec<u16>), FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.rasterize_indexed_sdf_u16(index, px))
+    }
+
+    pub fn try_rasterize_indexed_sdf_u16_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> Result<(Metrics, Vec<u16>), FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.rasterize_indexed_sdf_u16_with(index, px, settings))
+    }
+
+    pub fn try_rasterize_indexed_sdf_f16(
+        &self,
+        index: u16,
+        px: f32,
+    ) -> Result<(Metrics, Vec<f16>), FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.rasterize_indexed_sdf_f16(index, px))
+    }
+
+    pub fn try_rasterize_indexed_sdf_f16_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> Result<(Metrics, Vec<f16>), FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.rasterize_indexed_sdf_f16_with(index, px, settings))
+    }
+
+    pub fn try_rasterize_indexed_msdf(
+        &self,
+        index: u16,
+        px: f32,
+    ) -> Result<(Metrics, Vec<u8>), FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.rasterize_indexed_msdf(index, px))
+    }
+
+    pub fn try_rasterize_indexed_msdf_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> Result<(Metrics, Vec<u8>), FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.rasterize_indexed_msdf_with(index, px, settings))
+    }
+
+    pub fn try_rasterize_indexed_mtsdf(
+        &self,
+        index: u16,
+        px: f32,
+    ) -> Result<(Metrics, Vec<u8>), FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.rasterize_indexed_mtsdf(index, px))
+    }
+
+    pub fn try_rasterize_indexed_mtsdf_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> Result<(Metrics, Vec<u8>), FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.rasterize_indexed_mtsdf_with(index, px, settings))
+    }
+
+    pub fn try_rasterize_indexed_dual_sdf(
+        &self,
+        index: u16,
+        px: f32,
+    ) -> Result<(Metrics, Vec<u8>), FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.rasterize_indexed_dual_sdf(index, px))
+    }
+
+    pub fn try_rasterize_indexed_dual_sdf_with(
+        &self,
+        index: u16,
+        px: f32,
+        settings: &SdfSettings,
+    ) -> Result<(Metrics, Vec<u8>), FontSdfError> {
+        self.check_glyph(index)?;
+        Ok(self.rasterize_indexed_dual_sdf_with(index, px, settings))
+    }
+
     pub fn geometry(&self, character: char) -> &'_ (Geometry, Rect) {
         self.geometry_indexed(self.lookup_glyph_index(character))
     }
 
+    /// the glyph is outlined on the first call and cached
+    ///
+    /// # Panics
+    ///
+    /// if the glyph index is not in the font, see [`Self::try_geometry_indexed`]
     pub fn geometry_indexed(&self, index: u16) -> &'_ (Geometry, Rect) {
-        self.glyphs.get(index as usize).unwrap()
+        self.try_geometry_indexed(index).unwrap()
     }
 
     #[inline(always)]
@@ -204,7 +1041,7 @@ impl Font {
         if sdf {
             self.metrics_sdf(character, px)
         } else {
-            self.inner.metrics(character, px)
+            self.inner().metrics(character, px)
         }
     }
 
@@ -213,7 +1050,7 @@ impl Font {
         if sdf {
             self.metrics_indexed_sdf(index, px)
         } else {
-            self.inner.metrics_indexed(index, px)
+            self.inner().metrics_indexed(index, px)
         }
     }
 
@@ -222,7 +1059,7 @@ impl Font {
         if sdf {
             self.rasterize_sdf(character, px)
         } else {
-            self.inner.rasterize(character, px)
+            self.inner().rasterize(character, px)
         }
     }
 
@@ -231,17 +1068,223 @@ impl Font {
         if sdf {
             self.rasterize_indexed_sdf(index, px)
         } else {
-            self.inner.rasterize_indexed(index, px)
+            self.inner().rasterize_indexed(index, px)
         }
     }
 
     pub fn lookup_glyph_index(&self, ch: char) -> u16 {
-        self.inner.lookup_glyph_index(ch)
+        self.chars.get(&ch).map_or(0, |glyph| glyph.get())
+    }
+
+    pub fn horizontal_kern(&self, left: char, right: char, px: f32) -> Option<f32> {
+        self.horizontal_kern_indexed(
+            self.lookup_glyph_index(left),
+            self.lookup_glyph_index(right),
+            px,
+        )
+    }
+
+    /// kerning in pixels from the GPOS or kern table, `None` if there
+    /// is no kerning for the pair, not affected by
+    /// the sdf padding so it can be added to the pen position as is,
+    /// `px` is the render size and not the one the sdf was made at
+    pub fn horizontal_kern_indexed(&self, left: u16, right: u16, px: f32) -> Option<f32> {
+        let kern = self.kern_font_units(None, GlyphId(left), GlyphId(right))?;
+        Some(kern as f32 * self.scale_factor(px))
+    }
+
+    /// [`Self::horizontal_kern_indexed`] with an already parsed face,
+    /// for looking up many pairs that are not in the cache
+    pub(crate) fn horizontal_kern_indexed_in(
+        &self,
+        face: &Face,
+        left: u16,
+        right: u16,
+        px: f32,
+    ) -> Option<f32> {
+        let kern = self.kern_font_units(Some(face), GlyphId(left), GlyphId(right))?;
+        Some(kern as f32 * self.scale_factor(px))
+    }
+
+    /// the advance of the glyph in pixels at the render size `px`,
+    /// same as [`Metrics::advance_width`] of [`Self::metrics_indexed_sdf`],
+    /// `0.0` if the glyph index is not in the font
+    pub fn horizontal_advance_indexed(&self, index: u16, px: f32) -> f32 {
+        let (advance_width, _) = self
+            .advances
+            .get(index as usize)
+            .copied()
+            .unwrap_or_default();
+        advance_width * self.scale_factor(px)
+    }
+
+    /// line metrics in pixels at the render size `px`
+    pub fn horizontal_line_metrics(&self, px: f32) -> Option<LineMetrics> {
+        self.line_metrics
+            .0
+            .map(|metrics| self.scale_line_metrics(metrics, px))
+    }
+
+    /// line metrics in pixels at the render size `px`
+    pub fn vertical_line_metrics(&self, px: f32) -> Option<LineMetrics> {
+        self.line_metrics
+            .1
+            .map(|metrics| self.scale_line_metrics(metrics, px))
+    }
+
+    fn scale_line_metrics(&self, metrics: LineMetrics, px: f32) -> LineMetrics {
+        let sf = self.scale_factor(px);
+        LineMetrics {
+            ascent: metrics.ascent * sf,
+            descent: metrics.descent * sf,
+            line_gap: metrics.line_gap * sf,
+            new_line_size: metrics.new_line_size * sf,
+        }
+    }
+
+    /// the glyph exists and has an outline if it has anything
+    fn check_glyph(&self, index: u16) -> Result<(), FontSdfError> {
+        let (_, bb) = self.try_geometry_indexed(index)?;
+        if bb.width() != 0 || bb.height() != 0 {
+            return Ok(());
+        }
+
+        let face = self.face();
+        let id = GlyphId(index);
+        if face.glyph_raster_image(id, u16::MAX).is_some()
+            || face.glyph_svg_image(id).is_some()
+            || face.is_color_glyph(id)
+        {
+            return Err(FontSdfError::UnsupportedOutline(index));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn face(&self) -> Face<'_> {
+        // already parsed successfully in the constructor
+        let mut face = Face::parse(&self.data, self.settings.collection_index).unwrap();
+        for &(axis, value) in &self.variations {
+            face.set_variation(axis, value);
+        }
+        face
+    }
+
+    fn load_glyphs(&mut self, face: &Face) {
+        let count = face.number_of_glyphs() as usize;
+        self.glyphs = (0..count).map(|_| OnceBox::new()).collect();
+
+        self.advances = (0..count as u16)
+            .map(|i| {
+                let advance = |a: Option<u16>| a.unwrap_or(0) as f32;
+                (
+                    advance(face.glyph_hor_advance(GlyphId(i))),
+                    advance(face.glyph_ver_advance(GlyphId(i))),
+                )
+            })
+            .collect();
+    }
+
+    fn glyph(&self, index: u16) -> Result<&'_ Glyph, FontSdfError> {
+        let glyph = self
+            .glyphs
+            .get(index as usize)
+            .ok_or(FontSdfError::GlyphIndex(index))?;
+        Ok(glyph.get_or_init(|| {
+            Box::new(Glyph {
+                geometry: self.outline_glyph(index),
+                flattened: [const { OnceBox::new() }; FLATTEN_LEVELS],
+            })
+        }))
+    }
+
+    /// the lines of the glyph within [`CURVE_TOLERANCE`] pixels of the curves
+    fn flattened(&self, index: u16, sf: f32) -> &'_ Flattened {
+        let glyph = self.glyph(index).unwrap();
+        let (level, tolerance) = flatten_level(CURVE_TOLERANCE / sf);
+        glyph.flattened[level].get_or_init(|| Box::new(glyph.geometry.0.flatten(tolerance)))
     }
 
-    fn internal_metrics(&self, px: f32, bb: &Rect) -> InternalMetrics {
+    fn outline_glyph(&self, index: u16) -> (Geometry, Rect) {
+        let mut geom = Geometry::new();
+        match self.face().outline_glyph(GlyphId(index), &mut geom) {
+            Some(bb) => (geom, bb),
+            None => (
+                Geometry::new(),
+                Rect {
+                    x_min: 0,
+                    y_min: 0,
+                    x_max: 0,
+                    y_max: 0,
+                },
+            ),
+        }
+    }
+
+    fn kern_font_units(&self, face: Option<&Face>, left: GlyphId, right: GlyphId) -> Option<i16> {
+        if let Some(kern) = self.kerning.get(left, right) {
+            return kern;
+        }
+        let kern = match face {
+            Some(face) => self.load_kerning(face, left, right),
+            None => self.load_kerning(&self.face(), left, right),
+        };
+        self.kerning.insert(left, right, kern);
+        kern
+    }
+
+    /// kerning of one glyph pair, the tables are only looked up and
+    /// never listed, so the time doesn't grow with the glyph count
+    fn load_kerning(&self, face: &Face, left: GlyphId, right: GlyphId) -> Option<i16> {
+        let tables = face.tables();
+
+        // pair adjustments of the kern feature, the first subtable of a
+        // lookup that has the pair applies, and the lookups add up
+        if let Some(gpos) = tables.gpos {
+            let lookups = self.kern_lookups.get_or_init(|| {
+                let mut lookups: Vec<u16> = gpos
+                    .features
+                    .into_iter()
+                    .filter(|feature| feature.tag == Tag::from_bytes(b"kern"))
+                    .flat_map(|feature| feature.lookup_indices)
+                    .collect();
+                // the same lookups are usually listed for each script
+                lookups.sort_unstable();
+                lookups.dedup();
+                Box::new(lookups)
+            });
+
+            let kern = lookups
+                .iter()
+                .filter_map(|&lookup| gpos.lookups.get(lookup))
+                .filter_map(|lookup| {
+                    lookup
+                        .subtables
+                        .into_iter::<PositioningSubtable>()
+                        .find_map(|subtable| match subtable {
+                            PositioningSubtable::Pair(pair) => pair_kerning(pair, left, right),
+                            _ => None,
+                        })
+                })
+                .fold(0i16, i16::saturating_add);
+            if kern != 0 {
+                return Some(kern);
+            }
+        }
+
+        // the old kern table is only used for the pairs that GPOS doesn't kern
+        tables
+            .kern?
+            .subtables
+            .into_iter()
+            .filter(|subtable| subtable.horizontal && !subtable.variable)
+            .find_map(|subtable| subtable.glyphs_kerning(left, right))
+            .filter(|kern| *kern != 0)
+    }
+
+    fn internal_metrics(&self, px: f32, bb: &Rect, settings: &SdfSettings) -> InternalMetrics {
         let sf = self.scale_factor(px);
-        let radius = self.radius(px);
+        let radius = self.radius_with(px, settings);
+        let spread = settings.spread.pixels(px, sf) / sf;
         let offset_x = bb.x_min as f32 * sf;
         let offset_y = bb.y_min as f32 * sf;
 
@@ -262,6 +1305,8 @@ impl Font {
         InternalMetrics {
             sf,
             radius,
+            edge_value: settings.edge_value,
+            value_scale: settings.spread_value / spread,
             offset_x,
             offset_y,
             width,
@@ -277,11 +1322,181 @@ impl Font {
         width: usize,
         height: usize,
     ) -> Metrics {
-        let mut metrics = self.inner.metrics_indexed(index, px);
-        metrics.xmin -= radius as i32;
-        metrics.ymin -= radius as i32;
-        metrics.width = width;
-        metrics.height = height;
-        metrics
+        let sf = self.scale_factor(px);
+        let (_, bb) = self.geometry_indexed(index);
+        let (advance_width, advance_height) = self
+            .advances
+            .get(index as usize)
+            .copied()
+            .unwrap_or_default();
+        let bounds = OutlineBounds {
+            xmin: bb.x_min as f32 * sf,
+            ymin: bb.y_min as f32 * sf,
+            width: bb.width() as f32 * sf,
+            height: bb.height() as f32 * sf,
+        };
+        Metrics {
+            xmin: bounds.xmin.floor() as i32 - radius as i32,
+            ymin: bounds.ymin.floor() as i32 - radius as i32,
+            width,
+            height,
+            advance_width: advance_width * sf,
+            advance_height: advance_height * sf,
+            bounds,
+        }
+    }
+}
+
+impl InternalMetrics {
+    /// signed distance (in font units) to an output value,
+    /// the distances can be far outside the spread
+    fn to_u8(&self, d: Vec4) -> UVec4 {
+        (d * (self.value_scale * 255.0) + Vec4::splat(self.edge_value * 255.0))
+            .clamp(Vec4::ZERO, Vec4::splat(255.0))
+            .as_uvec4()
+    }
+
+    /// same mapping as [`Self::to_u8`] but to `0..=65535`
+    fn to_u16(&self, d: Vec4) -> UVec4 {
+        (d * (self.value_scale * 65535.0) + Vec4::splat(self.edge_value * 65535.0))
+            .clamp(Vec4::ZERO, Vec4::splat(65535.0))
+            .as_uvec4()
+    }
+
+    /// same mapping as [`Self::to_u8`] but to `0.0..=1.0`
+    fn to_unorm(&self, d: Vec4) -> Vec4 {
+        (d * self.value_scale + Vec4::splat(self.edge_value)).clamp(Vec4::ZERO, Vec4::ONE)
+    }
+
+    /// number of 4 pixel chunks, rounded up
+    fn chunk_count(&self) -> usize {
+        (self.width * self.height).div_ceil(4)
     }
+
+    /// index of the first pixel and the glyph space
+    /// positions of each chunk of 4 pixels
+    ///
+    /// the last chunk can go past the last pixel
+    fn pixels(&self) -> impl Iterator<Item = (usize, (Vec4, Vec4))> + '_ {
+        let inv_sf = 1.0 / self.sf;
+        let w = self.width as u32;
+        let h = self.height as u32;
+        (0..self.chunk_count() as u32).map(|i| i * 4).map(move |i| {
+            (
+                i as usize,
+                (
+                    (UVec4::new(i % w, (i + 1) % w, (i + 2) % w, (i + 3) % w).as_vec4()
+                        - self.radius as f32
+                        + self.offset_x)
+                        * inv_sf,
+                    (UVec4::new(
+                        (h - 1).saturating_sub(i / w),
+                        (h - 1).saturating_sub((i + 1) / w),
+                        (h - 1).saturating_sub((i + 2) / w),
+                        (h - 1).saturating_sub((i + 3) / w),
+                    )
+                    .as_vec4()
+                        - self.radius as f32
+                        + self.offset_y)
+                        * inv_sf,
+                ),
+            )
+        })
+    }
+}
+
+impl MultiChannel {
+    fn channels(self) -> usize {
+        match self {
+            MultiChannel::Msdf => 3,
+            MultiChannel::Mtsdf => 4,
+            MultiChannel::Dual => 2,
+        }
+    }
+}
+
+impl KerningCache {
+    const SLOTS: usize = 4096;
+    const FILLED: u64 = 1 << 49;
+
+    fn slot(&self, left: GlyphId, right: GlyphId) -> (&AtomicU64, u64) {
+        let slots = self
+            .slots
+            .get_or_init(|| Box::new((0..Self::SLOTS).map(|_| AtomicU64::new(0)).collect()));
+        let key = (left.0 as u64) << 16 | right.0 as u64;
+        // fibonacci hashing, neighbouring pairs end up far apart
+        let hash = (key as u32).wrapping_mul(0x9E37_79B9) >> (32 - Self::SLOTS.trailing_zeros());
+        (&slots[hash as usize], Self::FILLED | key << 17)
+    }
+
+    fn get(&self, left: GlyphId, right: GlyphId) -> Option<Option<i16>> {
+        let (slot, key) = self.slot(left, right);
+        let value = slot.load(Ordering::Relaxed);
+        if value & !0x1_FFFF != key {
+            return None;
+        }
+        let kern = value as u16 as i16;
+        Some((value & 1 << 16 != 0).then_some(kern))
+    }
+
+    fn insert(&self, left: GlyphId, right: GlyphId, kern: Option<i16>) {
+        let (slot, key) = self.slot(left, right);
+        let value = match kern {
+            Some(kern) => 1 << 16 | kern as u16 as u64,
+            None => 0,
+        };
+        slot.store(key | value, Ordering::Relaxed);
+    }
+}
+
+impl Clone for KerningCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for KerningCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KerningCache").finish_non_exhaustive()
+    }
+}
+
+//
+
+/// the constructor already checked everything that
+/// fontdue checks, except for the substitutions
+fn load_inner(data: &[u8], settings: FontSettings) -> fontdue::Font {
+    fontdue::Font::from_bytes(data, settings)
+        .or_else(|_| {
+            let settings = FontSettings {
+                load_substitutions: false,
+                ..settings
+            };
+            fontdue::Font::from_bytes(data, settings)
+        })
+        .expect("checked in the constructor")
+}
+
+/// x advance of the first glyph from one GPOS pair adjustment
+/// subtable, `None` if the subtable doesn't have the pair
+fn pair_kerning(pair: PairAdjustment, left: GlyphId, right: GlyphId) -> Option<i16> {
+    let coverage_index = pair.coverage().get(left)?;
+    let (first, _) = match pair {
+        PairAdjustment::Format1 { sets, .. } => sets.get(coverage_index)?.get(right)?,
+        PairAdjustment::Format2 {
+            classes, matrix, ..
+        } => matrix.get((classes.0.get(left), classes.1.get(right)))?,
+    };
+    Some(first.x_advance)
+}
+
+/// the cache index and tolerance of the largest cached
+/// tolerance level at or below `tolerance` (in font units)
+fn flatten_level(tolerance: f32) -> (usize, f32) {
+    // floor(log2(tolerance)) from the exponent bits,
+    // anything weird like infinity or negatives ends up clamped
+    let exponent = ((tolerance.to_bits() >> 23) as i32 - 127)
+        .clamp(FLATTEN_MIN_EXPONENT, FLATTEN_MAX_EXPONENT);
+    let level = (exponent - FLATTEN_MIN_EXPONENT) as usize;
+    (level, f32::from_bits(((exponent + 127) as u32) << 23))
 }
@@ -1,9 +1,14 @@
+use alloc::vec::Vec;
 use glam::{BVec4A, UVec4, Vec2, Vec4};
 
-use crate::geom::Contour;
+use crate::geom::FlatContour;
 
 //
 
+/// evenly spaced points per curve, used as the starting points
+/// of the closest point search and for distance upper bounds
+pub const CURVE_SAMPLES: usize = 8;
+
 #[derive(Debug, Clone, Copy)]
 pub struct Line {
     pub from: Vec2,
@@ -25,6 +30,14 @@ pub struct Curve {
     pub to: Vec2,
 }
 
+/// any one of the segments, as it was in the outline
+#[derive(Debug, Clone, Copy)]
+pub enum Edge {
+    Line(Line),
+    Quad(Quad),
+    Curve(Curve),
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 pub struct BoundingBox {
     pub min: Vec2,
@@ -40,10 +53,20 @@ pub struct Ray {
     pub to_y: Vec4,
 }
 
-pub trait Segment: Sized {
+/// a curve as `c[0] + c[1] t + c[2] t^2 + c[3] t^3`,
+/// evaluated for 4 parameters at once
+#[derive(Debug, Clone, Copy)]
+struct Polynomial {
+    c: [Vec2; 4],
+}
+
+pub trait Segment: Sized + Into<Edge> {
     fn aabb(self) -> BoundingBox;
     fn iter_lines(self, resolution: usize) -> impl ExactSizeIterator<Item = Line>;
+    /// number of lines needed to stay within `tolerance` of the segment
+    fn resolution(self, tolerance: f32) -> usize;
     fn control_points(self) -> impl ExactSizeIterator<Item = Vec2>;
+    fn split(self, t: f32) -> (Self, Self);
 }
 
 //
@@ -107,7 +130,7 @@ impl BoundingBox {
 }
 
 impl Ray {
-    pub fn hit_count(self, other: &Contour) -> Vec4 {
+    pub fn hit_count(self, other: &FlatContour) -> Vec4 {
         let bb_min_x = self.from_x.min(self.to_x);
         let bb_min_y = self.from_y.min(self.to_y);
         let bb_max_x = self.from_x.max(self.to_x);
@@ -148,9 +171,27 @@ impl Segment for Line {
         core::iter::once(self)
     }
 
+    fn resolution(self, _: f32) -> usize {
+        1
+    }
+
     fn control_points(self) -> impl ExactSizeIterator<Item = Vec2> {
         [self.from, self.to].into_iter()
     }
+
+    fn split(self, t: f32) -> (Self, Self) {
+        let mid = self.from.lerp(self.to, t);
+        (
+            Line {
+                from: self.from,
+                to: mid,
+            },
+            Line {
+                from: mid,
+                to: self.to,
+            },
+        )
+    }
 }
 
 impl Segment for Quad {
@@ -180,9 +221,34 @@ impl Segment for Quad {
         })
     }
 
+    fn resolution(self, tolerance: f32) -> usize {
+        // the distance between a line and the curve between
+        // t and t + 1/n is at most |B''| / (8 * n^2)
+        let dd = (self.from - 2.0 * self.by + self.to).length();
+        curve_resolution(dd * 0.25, tolerance)
+    }
+
     fn control_points(self) -> impl ExactSizeIterator<Item = Vec2> {
         [self.from, self.by, self.to].into_iter()
     }
+
+    fn split(self, t: f32) -> (Self, Self) {
+        let from_by = self.from.lerp(self.by, t);
+        let by_to = self.by.lerp(self.to, t);
+        let mid = from_by.lerp(by_to, t);
+        (
+            Quad {
+                from: self.from,
+                by: from_by,
+                to: mid,
+            },
+            Quad {
+                from: mid,
+                by: by_to,
+                to: self.to,
+            },
+        )
+    }
 }
 
 impl Segment for Curve {
@@ -217,9 +283,369 @@ impl Segment for Curve {
         })
     }
 
+    fn resolution(self, tolerance: f32) -> usize {
+        // same as with quads, |B''| is at most 6 times
+        // the larger of the two second differences
+        let dd = (self.from - 2.0 * self.by_a + self.by_b)
+            .length()
+            .max((self.by_a - 2.0 * self.by_b + self.to).length());
+        curve_resolution(dd * 0.75, tolerance)
+    }
+
     fn control_points(self) -> impl ExactSizeIterator<Item = Vec2> {
         [self.from, self.by_a, self.by_b, self.to].into_iter()
     }
+
+    fn split(self, t: f32) -> (Self, Self) {
+        let from_by_a = self.from.lerp(self.by_a, t);
+        let by_a_by_b = self.by_a.lerp(self.by_b, t);
+        let by_b_to = self.by_b.lerp(self.to, t);
+
+        let from_by_a_by_a_by_b = from_by_a.lerp(by_a_by_b, t);
+        let by_a_by_b_by_b_to = by_a_by_b.lerp(by_b_to, t);
+
+        let mid = from_by_a_by_a_by_b.lerp(by_a_by_b_by_b_to, t);
+        (
+            Curve {
+                from: self.from,
+                by_a: from_by_a,
+                by_b: from_by_a_by_a_by_b,
+                to: mid,
+            },
+            Curve {
+                from: mid,
+                by_a: by_a_by_b_by_b_to,
+                by_b: by_b_to,
+                to: self.to,
+            },
+        )
+    }
+}
+
+impl Edge {
+    pub fn aabb(self) -> BoundingBox {
+        match self {
+            Edge::Line(line) => line.aabb(),
+            Edge::Quad(quad) => quad.aabb(),
+            Edge::Curve(curve) => curve.aabb(),
+        }
+    }
+
+    /// normalized direction of the curve at its start
+    pub fn start_direction(self) -> Vec2 {
+        let (control_points, n) = self.control_points();
+        tangent(control_points[..n].iter().copied())
+    }
+
+    /// normalized direction of the curve at its end
+    pub fn end_direction(self) -> Vec2 {
+        let (control_points, n) = self.control_points();
+        -tangent(control_points[..n].iter().rev().copied())
+    }
+
+    /// the exact squared distance to this edge
+    pub fn distance_ord(self, p: (Vec4, Vec4)) -> Vec4 {
+        match self {
+            Edge::Line(line) => line.distance_ord(p),
+            Edge::Quad(quad) => quad.closest(p).1,
+            Edge::Curve(curve) => curve.closest(p).1,
+        }
+    }
+
+    pub fn point(self, t: f32) -> Vec2 {
+        match self {
+            Edge::Line(line) => line.from.lerp(line.to, t),
+            Edge::Quad(quad) => quad.point(t),
+            Edge::Curve(curve) => curve.point(t),
+        }
+    }
+
+    /// see [`Line::pseudo_distance`], for curves the projection parameter
+    /// is only outside of `0.0..=1.0` if the closest point is one of the ends,
+    /// and the perpendicular distance is then to the tangent at that end
+    pub fn pseudo_distance(self, p: (Vec4, Vec4)) -> (Vec4, Vec4, Vec4) {
+        let (polynomial, (t, distance_squared)) = match self {
+            Edge::Line(line) => return line.pseudo_distance(p),
+            Edge::Quad(quad) => (Polynomial::from(quad), quad.closest(p)),
+            Edge::Curve(curve) => (Polynomial::from(curve), curve.closest(p)),
+        };
+        let (control_points, n) = self.control_points();
+        let (from, to) = (control_points[0], control_points[n - 1]);
+
+        // the sign comes from the side of the tangent at the closest point,
+        // or of the chord where the curve has no direction
+        let (x, y) = polynomial.point(t);
+        let (dx, dy) = polynomial.direction(t);
+        let stopped = dx.cmpeq(Vec4::ZERO) & dy.cmpeq(Vec4::ZERO);
+        let dx = Vec4::select(stopped, Vec4::splat(to.x - from.x), dx);
+        let dy = Vec4::select(stopped, Vec4::splat(to.y - from.y), dy);
+        let side = (dx * (p.1 - y) - dy * (p.0 - x)).signum();
+        let mut perpendicular = side * distance_squared.powf(0.5);
+        let mut t = t;
+
+        let start = Line {
+            from,
+            to: from + self.start_direction(),
+        }
+        .pseudo_distance(p);
+        let before = t.cmple(Vec4::ZERO) & start.1.cmplt(Vec4::ZERO);
+        let end = Line {
+            from: to - self.end_direction(),
+            to,
+        }
+        .pseudo_distance(p);
+        let after = t.cmpge(Vec4::ONE) & end.1.cmpgt(Vec4::ONE);
+
+        t = Vec4::select(before, start.1, Vec4::select(after, end.1, t));
+        perpendicular = Vec4::select(before, start.2, Vec4::select(after, end.2, perpendicular));
+        (distance_squared, t, perpendicular)
+    }
+
+    pub fn split_in_thirds(self) -> [Edge; 3] {
+        fn thirds<S: Segment>(segment: S) -> [Edge; 3] {
+            let (a, rest) = segment.split(1.0 / 3.0);
+            let (b, c) = rest.split(0.5);
+            [a.into(), b.into(), c.into()]
+        }
+
+        match self {
+            Edge::Line(line) => thirds(line),
+            Edge::Quad(quad) => thirds(quad),
+            Edge::Curve(curve) => thirds(curve),
+        }
+    }
+
+    /// flatten this edge so that none of the lines
+    /// are further than `tolerance` from the curve
+    pub fn flatten_into(self, tolerance: f32, lines: &mut Vec<Line>) {
+        fn flatten<S: Segment + Copy>(segment: S, tolerance: f32, lines: &mut Vec<Line>) {
+            lines.extend(segment.iter_lines(segment.resolution(tolerance)));
+        }
+
+        match self {
+            Edge::Line(line) => flatten(line, tolerance, lines),
+            Edge::Quad(quad) => flatten(quad, tolerance, lines),
+            Edge::Curve(curve) => flatten(curve, tolerance, lines),
+        }
+    }
+
+    fn control_points(self) -> ([Vec2; 4], usize) {
+        match self {
+            Edge::Line(line) => ([line.from, line.to, Vec2::ZERO, Vec2::ZERO], 2),
+            Edge::Quad(quad) => ([quad.from, quad.by, quad.to, Vec2::ZERO], 3),
+            Edge::Curve(curve) => ([curve.from, curve.by_a, curve.by_b, curve.to], 4),
+        }
+    }
+}
+
+impl Polynomial {
+    fn point(self, t: Vec4) -> (Vec4, Vec4) {
+        let [c0, c1, c2, c3] = self.c;
+        let x = ((Vec4::splat(c3.x) * t + c2.x) * t + c1.x) * t + c0.x;
+        let y = ((Vec4::splat(c3.y) * t + c2.y) * t + c1.y) * t + c0.y;
+        (x, y)
+    }
+
+    fn direction(self, t: Vec4) -> (Vec4, Vec4) {
+        let [_, c1, c2, c3] = self.c;
+        let x = (Vec4::splat(3.0 * c3.x) * t + 2.0 * c2.x) * t + c1.x;
+        let y = (Vec4::splat(3.0 * c3.y) * t + 2.0 * c2.y) * t + c1.y;
+        (x, y)
+    }
+
+    fn second_derivative(self, t: Vec4) -> (Vec4, Vec4) {
+        let [_, _, c2, c3] = self.c;
+        let x = Vec4::splat(6.0 * c3.x) * t + 2.0 * c2.x;
+        let y = Vec4::splat(6.0 * c3.y) * t + 2.0 * c2.y;
+        (x, y)
+    }
+
+    fn distance_squared(self, t: Vec4, p: (Vec4, Vec4)) -> Vec4 {
+        let (x, y) = self.point(t);
+        let (x, y) = (x - p.0, y - p.1);
+        x * x + y * y
+    }
+}
+
+impl From<Quad> for Polynomial {
+    fn from(quad: Quad) -> Self {
+        Self {
+            c: [
+                quad.from,
+                2.0 * (quad.by - quad.from),
+                quad.from - 2.0 * quad.by + quad.to,
+                Vec2::ZERO,
+            ],
+        }
+    }
+}
+
+impl From<Curve> for Polynomial {
+    fn from(curve: Curve) -> Self {
+        Self {
+            c: [
+                curve.from,
+                3.0 * (curve.by_a - curve.from),
+                3.0 * (curve.from - 2.0 * curve.by_a + curve.by_b),
+                curve.to - 3.0 * curve.by_b + 3.0 * curve.by_a - curve.from,
+            ],
+        }
+    }
+}
+
+impl From<Line> for Edge {
+    fn from(value: Line) -> Self {
+        Edge::Line(value)
+    }
+}
+
+impl From<Quad> for Edge {
+    fn from(value: Quad) -> Self {
+        Edge::Quad(value)
+    }
+}
+
+impl From<Curve> for Edge {
+    fn from(value: Curve) -> Self {
+        Edge::Curve(value)
+    }
+}
+
+impl Quad {
+    pub fn point(self, t: f32) -> Vec2 {
+        let from_by = self.from.lerp(self.by, t);
+        let by_to = self.by.lerp(self.to, t);
+        from_by.lerp(by_to, t)
+    }
+
+    pub fn direction(self, t: f32) -> Vec2 {
+        (self.by - self.from).lerp(self.to - self.by, t) * 2.0
+    }
+
+    /// parameter and squared distance of the closest point to each of `p`
+    ///
+    /// the closest point is where `g(t) = (B(t) - p) . B'(t) = 0`, which is
+    /// a cubic equation for quadratic curves, the distance has its minima
+    /// where `g` goes from negative to positive, before the first and after
+    /// the second extremum of `g`, and each of those parts has at most one
+    fn closest(self, p: (Vec4, Vec4)) -> (Vec4, Vec4) {
+        const SEARCH_STEPS: usize = 8;
+
+        let a = self.by - self.from;
+        let b = self.to - self.by - a;
+        let m = (
+            Vec4::splat(self.from.x) - p.0,
+            Vec4::splat(self.from.y) - p.1,
+        );
+
+        // g(t) = c3 t^3 + c2 t^2 + c1 t + c0
+        let c3 = b.dot(b);
+        let c2 = 3.0 * a.dot(b);
+        let c1 = Vec4::splat(2.0 * a.dot(a)) + m.0 * b.x + m.1 * b.y;
+        let c0 = m.0 * a.x + m.1 * a.y;
+        let g = |t: Vec4| ((Vec4::splat(c3) * t + c2) * t + c1) * t + c0;
+        let dg = |t: Vec4| (Vec4::splat(3.0 * c3) * t + 2.0 * c2) * t + c1;
+
+        // without extrema g only increases, straight curves have c3 = 0
+        let discriminant = Vec4::splat(c2 * c2) - 3.0 * c3 * c1;
+        let has_extrema = discriminant.cmpgt(Vec4::ZERO) & BVec4A::splat(c3 > 0.0);
+        let root = discriminant.max(Vec4::ZERO).powf(0.5);
+        let extremum = |root: Vec4| {
+            let t = ((Vec4::splat(-c2) + root) / (3.0 * c3)).clamp(Vec4::ZERO, Vec4::ONE);
+            Vec4::select(has_extrema, t, Vec4::ZERO)
+        };
+
+        // Newton's method, falling back to bisection when it would
+        // leave the part, ends up at an end if there is no root
+        let search = |mut lo: Vec4, mut hi: Vec4| {
+            let mut t = (lo + hi) * 0.5;
+            for _ in 0..SEARCH_STEPS {
+                let gt = g(t);
+                let below = gt.cmplt(Vec4::ZERO);
+                lo = Vec4::select(below, t, lo);
+                hi = Vec4::select(below, hi, t);
+                let newton = t - gt / dg(t);
+                let inside = newton.cmpge(lo) & newton.cmple(hi);
+                t = Vec4::select(inside, newton, (lo + hi) * 0.5);
+            }
+            t
+        };
+
+        let polynomial = Polynomial::from(self);
+        [
+            Vec4::ZERO,
+            Vec4::ONE,
+            search(Vec4::ZERO, extremum(-root)),
+            search(extremum(root), Vec4::ONE),
+        ]
+        .into_iter()
+        .map(|t| (t, polynomial.distance_squared(t, p)))
+        .reduce(|best, next| {
+            let closer = next.1.cmplt(best.1);
+            (
+                Vec4::select(closer, next.0, best.0),
+                Vec4::select(closer, next.1, best.1),
+            )
+        })
+        .unwrap()
+    }
+}
+
+impl Curve {
+    pub fn point(self, t: f32) -> Vec2 {
+        let from_by_a = self.from.lerp(self.by_a, t);
+        let by_a_by_b = self.by_a.lerp(self.by_b, t);
+        let by_b_to = self.by_b.lerp(self.to, t);
+        from_by_a
+            .lerp(by_a_by_b, t)
+            .lerp(by_a_by_b.lerp(by_b_to, t), t)
+    }
+
+    pub fn direction(self, t: f32) -> Vec2 {
+        let a = self.by_a - self.from;
+        let b = self.by_b - self.by_a;
+        let c = self.to - self.by_b;
+        a.lerp(b, t).lerp(b.lerp(c, t), t) * 3.0
+    }
+
+    /// parameter and squared distance of the closest point to each of `p`
+    ///
+    /// there is no closed form for cubic curves, so this refines
+    /// evenly spaced starting points with Newton's method
+    fn closest(self, p: (Vec4, Vec4)) -> (Vec4, Vec4) {
+        const SEARCH_STEPS: usize = 4;
+
+        let polynomial = Polynomial::from(self);
+        let mut best_t = Vec4::ZERO;
+        let mut best = Vec4::INFINITY;
+        for start in 0..=CURVE_SAMPLES {
+            let mut t = Vec4::splat(start as f32 / CURVE_SAMPLES as f32);
+            for step in 0..=SEARCH_STEPS {
+                if step != 0 {
+                    let (x, y) = polynomial.point(t);
+                    let to_p = (x - p.0, y - p.1);
+                    let d1 = polynomial.direction(t);
+                    let d2 = polynomial.second_derivative(t);
+                    let numerator = to_p.0 * d1.0 + to_p.1 * d1.1;
+                    let denominator = d1.0 * d1.0 + d1.1 * d1.1 + to_p.0 * d2.0 + to_p.1 * d2.1;
+                    // stay in place instead of moving towards a furthest point
+                    let step = Vec4::select(
+                        denominator.cmpgt(Vec4::ZERO),
+                        numerator / denominator,
+                        Vec4::ZERO,
+                    );
+                    t = (t - step).clamp(Vec4::ZERO, Vec4::ONE);
+                }
+
+                let distance_squared = polynomial.distance_squared(t, p);
+                let closer = distance_squared.cmplt(best);
+                best_t = Vec4::select(closer, t, best_t);
+                best = best.min(distance_squared);
+            }
+        }
+
+        (best_t, best)
+    }
 }
 
 impl Line {
@@ -243,6 +669,25 @@ impl Line {
         tmp.0 * tmp.0 + tmp.1 * tmp.1
     }
 
+    /// squared distance, the unclamped projection parameter
+    /// and the signed perpendicular distance to the infinite line
+    ///
+    /// the perpendicular distance is positive on the left side
+    pub fn pseudo_distance(self, p: (Vec4, Vec4)) -> (Vec4, Vec4, Vec4) {
+        let a = (Vec4::splat(self.from.x), Vec4::splat(self.from.y));
+        let a_to_p = (p.0 - a.0, p.1 - a.1);
+        let a_to_b = self.to - self.from;
+        let length_squared = a_to_b.length_squared();
+
+        let t = (a_to_p.0 * a_to_b.x + a_to_p.1 * a_to_b.y) / length_squared;
+        // the same rounding as the single channel sdf, so that both agree exactly
+        let distance_squared = self.distance_ord(p);
+
+        let perpendicular = (a_to_b.x * a_to_p.1 - a_to_b.y * a_to_p.0) / length_squared.sqrt();
+
+        (distance_squared, t, perpendicular)
+    }
+
     pub fn side(self, p: (Vec4, Vec4)) -> Vec4 {
         let a = (Vec4::splat(self.from.x), Vec4::splat(self.from.y));
         let b = (Vec4::splat(self.to.x), Vec4::splat(self.to.y));
@@ -299,3 +744,23 @@ pub fn uvec4_to_bvec4(v: UVec4) -> BVec4A {
         //
     )
 }
+
+/// direction from the first control point to
+/// the first control point that is not equal to it
+fn tangent(mut control_points: impl Iterator<Item = Vec2>) -> Vec2 {
+    let Some(first) = control_points.next() else {
+        return Vec2::ZERO;
+    };
+    control_points
+        .find(|&p| p != first)
+        .map_or(Vec2::ZERO, |p| (p - first).normalize_or_zero())
+}
+
+/// `n` such that `error / n^2 <= tolerance`
+fn curve_resolution(error: f32, tolerance: f32) -> usize {
+    let n = (error / tolerance).sqrt().ceil();
+    if n.is_nan() {
+        return 1;
+    }
+    n.clamp(1.0, 256.0) as usize
+}
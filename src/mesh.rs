@@ -0,0 +1,187 @@
+use crate::layout::GlyphQuad;
+use alloc::vec::Vec;
+
+//
+
+/// one vertex attribute, all floats are `f32`:s
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VertexAttribute {
+    /// 2 floats, in the same pixels as the [`GlyphQuad`]:s
+    Position,
+    /// 2 floats
+    Uv,
+    /// 1 float, the page or the texture array layer
+    Page,
+    /// 4 floats
+    Color,
+    /// 4 bytes, the color in `0..=255`
+    ColorU8,
+    /// 4 floats of anything, like outline width or softness
+    Params,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum IndexFormat {
+    #[default]
+    U16,
+    U32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+/// interleaved vertices in native endian, ready to be uploaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<u8>,
+    /// bytes per vertex
+    pub stride: usize,
+    pub indices: Indices,
+}
+
+/// builds a [`Mesh`] with 4 vertices and 6 indices (2 triangles) per quad
+#[derive(Debug, Clone)]
+pub struct MeshBuilder {
+    layout: Vec<VertexAttribute>,
+    stride: usize,
+    vertices: Vec<u8>,
+    indices: Indices,
+    vertex_count: usize,
+}
+
+//
+
+impl VertexAttribute {
+    /// [`Self::Position`], [`Self::Uv`], [`Self::Color`]
+    pub const DEFAULT_LAYOUT: &[VertexAttribute] = &[Self::Position, Self::Uv, Self::Color];
+
+    pub fn size(self) -> usize {
+        match self {
+            VertexAttribute::Position | VertexAttribute::Uv => 8,
+            VertexAttribute::Page => 4,
+            VertexAttribute::Color | VertexAttribute::Params => 16,
+            VertexAttribute::ColorU8 => 4,
+        }
+    }
+
+    /// byte offset of each attribute in a vertex
+    pub fn offsets(layout: &[VertexAttribute]) -> impl Iterator<Item = usize> + '_ {
+        layout.iter().scan(0, |offset, attribute| {
+            let current = *offset;
+            *offset += attribute.size();
+            Some(current)
+        })
+    }
+}
+
+impl Indices {
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl MeshBuilder {
+    pub fn new(layout: &[VertexAttribute], index_format: IndexFormat) -> Self {
+        Self {
+            layout: layout.to_vec(),
+            stride: layout.iter().map(|attribute| attribute.size()).sum(),
+            vertices: Vec::new(),
+            indices: match index_format {
+                IndexFormat::U16 => Indices::U16(Vec::new()),
+                IndexFormat::U32 => Indices::U32(Vec::new()),
+            },
+            vertex_count: 0,
+        }
+    }
+
+    /// bytes per vertex
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn layout(&self) -> &[VertexAttribute] {
+        &self.layout
+    }
+
+    /// # Panics
+    ///
+    /// if there are more than 16384 quads with [`IndexFormat::U16`]
+    pub fn push_quad(&mut self, quad: &GlyphQuad, color: [f32; 4], params: [f32; 4]) {
+        // before anything is pushed, so that the mesh stays valid if the panic is caught
+        if let Indices::U16(_) = self.indices {
+            assert!(
+                self.vertex_count + 4 <= 1 << 16,
+                "too many quads for u16 indices"
+            );
+        }
+
+        // clockwise on screen, starting from the top left corner
+        let corners = [
+            ([quad.min.x, quad.min.y], [quad.uv_min.x, quad.uv_min.y]),
+            ([quad.max.x, quad.min.y], [quad.uv_max.x, quad.uv_min.y]),
+            ([quad.max.x, quad.max.y], [quad.uv_max.x, quad.uv_max.y]),
+            ([quad.min.x, quad.max.y], [quad.uv_min.x, quad.uv_max.y]),
+        ];
+
+        for (position, uv) in corners {
+            for attribute in &self.layout {
+                match attribute {
+                    VertexAttribute::Position => push_floats(&mut self.vertices, &position),
+                    VertexAttribute::Uv => push_floats(&mut self.vertices, &uv),
+                    VertexAttribute::Page => push_floats(&mut self.vertices, &[quad.page as f32]),
+                    VertexAttribute::Color => push_floats(&mut self.vertices, &color),
+                    VertexAttribute::ColorU8 => self
+                        .vertices
+                        .extend(color.map(|c| (c.clamp(0.0, 1.0) * 255.0 + 0.5) as u8)),
+                    VertexAttribute::Params => push_floats(&mut self.vertices, &params),
+                }
+            }
+        }
+
+        let first = self.vertex_count;
+        self.vertex_count += 4;
+        let quad_indices = [0, 1, 2, 0, 2, 3].map(|i| first + i);
+        match &mut self.indices {
+            Indices::U16(indices) => indices.extend(quad_indices.map(|i| i as u16)),
+            Indices::U32(indices) => indices.extend(quad_indices.map(|i| i as u32)),
+        }
+    }
+
+    pub fn push_quads(&mut self, quads: &[GlyphQuad], color: [f32; 4], params: [f32; 4]) {
+        for quad in quads {
+            self.push_quad(quad, color, params);
+        }
+    }
+
+    pub fn build(self) -> Mesh {
+        Mesh {
+            vertices: self.vertices,
+            stride: self.stride,
+            indices: self.indices,
+        }
+    }
+}
+
+impl Default for MeshBuilder {
+    fn default() -> Self {
+        Self::new(VertexAttribute::DEFAULT_LAYOUT, IndexFormat::default())
+    }
+}
+
+//
+
+fn push_floats(vertices: &mut Vec<u8>, floats: &[f32]) {
+    for f in floats {
+        vertices.extend_from_slice(&f.to_ne_bytes());
+    }
+}
@@ -0,0 +1,61 @@
+/// how far the distance field reaches from the glyph edges
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Spread {
+    /// in output pixels, the same for every font size
+    Pixels(f32),
+    /// relative to the font size, `Em(0.125)` at 64px is 8 pixels
+    Em(f32),
+    /// in font units, scales with the glyphs like [`Spread::Em`]
+    /// but depends on the units per em of the font
+    FontUnits(f32),
+}
+
+/// unit of the unquantized distances
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DistanceUnit {
+    /// output pixels
+    #[default]
+    Pixels,
+    /// relative to the font size
+    Em,
+    /// font units, as in the [`crate::geom::Geometry`]
+    FontUnits,
+}
+
+/// distance field settings for the `_with` rasterization functions
+///
+/// the output value of a pixel is
+/// `edge_value + spread_value * distance / spread`,
+/// where `distance` is positive inside the glyph
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SdfSettings {
+    /// the padding around the glyphs is also based on this
+    pub spread: Spread,
+    /// output value of the glyph edges, `0.0` is black and `1.0` is white
+    pub edge_value: f32,
+    /// how much the output value changes over `spread`
+    pub spread_value: f32,
+}
+
+//
+
+impl Spread {
+    /// the spread in output pixels
+    pub fn pixels(self, px: f32, scale_factor: f32) -> f32 {
+        match self {
+            Spread::Pixels(pixels) => pixels,
+            Spread::Em(em) => em * px,
+            Spread::FontUnits(units) => units * scale_factor,
+        }
+    }
+}
+
+impl Default for SdfSettings {
+    fn default() -> Self {
+        Self {
+            spread: Spread::FontUnits(255.0),
+            edge_value: 128.0 / 255.0,
+            spread_value: 0.5,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! text shaping with [`rustybuzz`], enabled with the `shaping` feature
+//!
+//! the shaped glyph indices can be given to [`Font::rasterize_indexed_sdf`],
+//! [`Font::metrics_indexed_sdf`] or an [`Atlas`] made with [`Atlas::from_indices`]
+
+use crate::{
+    Font,
+    atlas::Atlas,
+    layout::{GlyphQuad, glyph_quad},
+};
+use alloc::vec::Vec;
+use glam::Vec2;
+
+//
+
+pub use rustybuzz::{Direction, Feature, Language, Script, UnicodeBuffer};
+
+/// a glyph positioned by the shaper, in pixels and y grows downwards
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapedGlyph {
+    pub index: u16,
+    /// byte offset of the first char of the cluster in the text
+    pub byte_offset: usize,
+    /// the glyph origin on the baseline, the first
+    /// glyph is shaped at `(0, 0)`
+    pub position: Vec2,
+    /// how much the pen moves after this glyph
+    pub advance: Vec2,
+}
+
+//
+
+/// shape `text` with the default features, the direction,
+/// script and language are guessed from the text
+pub fn shape(font: &Font, text: &str, px: f32) -> Vec<ShapedGlyph> {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    shape_with(font, buffer, px, &[])
+}
+
+/// shape a prepared buffer with extra `features`, like `liga` off or `smcp` on
+///
+/// the font variations from [`Font::set_variations`] are used
+pub fn shape_with(
+    font: &Font,
+    buffer: UnicodeBuffer,
+    px: f32,
+    features: &[Feature],
+) -> Vec<ShapedGlyph> {
+    let face = rustybuzz::Face::from_face(font.face());
+    let output = rustybuzz::shape(&face, features, buffer);
+    let sf = font.scale_factor(px);
+
+    let mut pen = Vec2::ZERO;
+    output
+        .glyph_infos()
+        .iter()
+        .zip(output.glyph_positions())
+        .map(|(info, pos)| {
+            let offset = Vec2::new(pos.x_offset as f32, -pos.y_offset as f32) * sf;
+            let advance = Vec2::new(pos.x_advance as f32, -pos.y_advance as f32) * sf;
+            let glyph = ShapedGlyph {
+                index: info.glyph_id as u16,
+                byte_offset: info.cluster as usize,
+                position: pen + offset,
+                advance,
+            };
+            pen += advance;
+            glyph
+        })
+        .collect()
+}
+
+/// quads for shaped glyphs drawn at `origin`, like [`crate::layout::layout`]
+/// but without line breaking
+///
+/// `px` has to be the size the glyphs were shaped at and
+/// `font` the one the atlas was made from
+pub fn quads(
+    font: &Font,
+    atlas: &Atlas,
+    glyphs: &[ShapedGlyph],
+    px: f32,
+    origin: Vec2,
+) -> Vec<GlyphQuad> {
+    let scale = px / atlas.px();
+    glyphs
+        .iter()
+        .filter_map(|glyph| {
+            glyph_quad(
+                font,
+                atlas,
+                glyph.index,
+                glyph.byte_offset,
+                origin + glyph.position,
+                scale,
+            )
+        })
+        .collect()
+}
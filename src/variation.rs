@@ -0,0 +1,66 @@
+use alloc::{string::String, vec::Vec};
+use ttf_parser::{Face, Tag};
+
+//
+
+/// a predefined instance of a variable font
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedInstance {
+    /// subfamily name, like "Bold Condensed"
+    pub name: Option<String>,
+    /// the value of every axis
+    pub coordinates: Vec<(Tag, f32)>,
+}
+
+//
+
+/// the instance records of the `fvar` table, ttf-parser only reads the axes
+pub(crate) fn named_instances(face: &Face) -> Vec<NamedInstance> {
+    (|| {
+        let fvar = face.raw_face().table(Tag::from_bytes(b"fvar"))?;
+        let u16_at = |offset: usize| {
+            Some(u16::from_be_bytes([
+                *fvar.get(offset)?,
+                *fvar.get(offset + 1)?,
+            ]))
+        };
+        let fixed_at = |offset: usize| {
+            let bytes = fvar.get(offset..offset + 4)?;
+            Some(i32::from_be_bytes(bytes.try_into().ok()?) as f32 / 65536.0)
+        };
+
+        let axes_offset = u16_at(4)? as usize;
+        let axis_count = u16_at(8)? as usize;
+        let axis_size = u16_at(10)? as usize;
+        let instance_count = u16_at(12)? as usize;
+        let instance_size = u16_at(14)? as usize;
+        let axes: Vec<Tag> = face
+            .variation_axes()
+            .into_iter()
+            .map(|axis| axis.tag)
+            .collect();
+        if axes.len() != axis_count {
+            return None;
+        }
+
+        let instances_offset = axes_offset + axis_count * axis_size;
+        (0..instance_count)
+            .map(|i| {
+                let offset = instances_offset + i * instance_size;
+                let name_id = u16_at(offset)?;
+                let coordinates = axes
+                    .iter()
+                    .enumerate()
+                    .map(|(j, axis)| Some((*axis, fixed_at(offset + 4 + j * 4)?)))
+                    .collect::<Option<Vec<_>>>()?;
+                let name = face
+                    .names()
+                    .into_iter()
+                    .filter(|name| name.name_id == name_id)
+                    .find_map(|name| name.to_string());
+                Some(NamedInstance { name, coordinates })
+            })
+            .collect::<Option<Vec<_>>>()
+    })()
+    .unwrap_or_default()
+}
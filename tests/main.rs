@@ -1,9 +1,19 @@
-use fontsdf::Font;
-use image::{GenericImage, GrayImage};
+use fontsdf::{
+    Atlas, AtlasSettings, CURVE_TOLERANCE, DistanceUnit, DynamicAtlas, Font, FontSdfError, FontSet,
+    FontSettings, NamedInstance, SdfSettings, Spread, Tag,
+    baked::BakedAtlas,
+    export, layout,
+    math::{Curve, Edge},
+    mesh::{IndexFormat, Indices, MeshBuilder, VertexAttribute},
+};
+use glam::{Vec2, Vec4};
+use image::{GenericImage, GrayImage, RgbImage};
 
 //
 
 const FONT_FILE: &[u8] = include_bytes!("../res/roboto/font.ttf");
+/// a tiny variable font, see `res/test/generate.py`
+const TEST_FONT_FILE: &[u8] = include_bytes!("../res/test/font.ttf");
 
 //
 
@@ -69,3 +79,896 @@ fn all_chars() {
 
     combined.save("test_all_chars.png").unwrap();
 }
+
+#[test]
+fn msdf_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+
+    for character in ['#', 'A', 'g', '@'] {
+        let (metrics, msdf) = font.rasterize_msdf(character, 64.0);
+        let (sdf_metrics, sdf) = font.rasterize_sdf(character, 64.0);
+        assert_eq!(metrics, sdf_metrics, "character was: {character}");
+        assert_eq!(msdf.len(), sdf.len() * 3, "character was: {character}");
+
+        // the median should agree with the single channel sdf on which side of the edge a pixel is
+        let mismatches = msdf
+            .chunks_exact(3)
+            .zip(sdf.iter())
+            .filter(|(rgb, d)| {
+                let median = rgb[0].max(rgb[1]).min(rgb[0].min(rgb[1]).max(rgb[2]));
+                (median >= 128) != (**d >= 128)
+            })
+            .count();
+        assert!(
+            mismatches * 100 < sdf.len(),
+            "character was: {character}, mismatches: {mismatches}"
+        );
+
+        RgbImage::from_raw(metrics.width as _, metrics.height as _, msdf)
+            .unwrap()
+            .save(format!("msdf_test_{}.png", character as u32))
+            .unwrap();
+    }
+
+    // pixels exactly on a straight edge must not be pushed off it
+    for character in ['-', 'B', 'l'] {
+        for px in [16.0, 48.0, 100.0] {
+            let (_, msdf) = font.rasterize_msdf(character, px);
+            let (_, sdf) = font.rasterize_sdf(character, px);
+
+            let mismatches = msdf
+                .chunks_exact(3)
+                .zip(sdf.iter())
+                .filter(|(rgb, d)| {
+                    let median = rgb[0].max(rgb[1]).min(rgb[0].min(rgb[1]).max(rgb[2]));
+                    **d == 128 && median != 128
+                })
+                .count();
+            assert_eq!(mismatches, 0, "character was: {character}, px: {px}");
+        }
+    }
+}
+
+#[test]
+fn mtsdf_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+
+    for character in ['#', 'A', 'g', '@'] {
+        let (metrics, mtsdf) = font.rasterize_mtsdf(character, 64.0);
+        let (_, msdf) = font.rasterize_msdf(character, 64.0);
+        let (sdf_metrics, sdf) = font.rasterize_sdf(character, 64.0);
+        assert_eq!(metrics, sdf_metrics, "character was: {character}");
+
+        for ((rgba, rgb), d) in mtsdf.chunks_exact(4).zip(msdf.chunks_exact(3)).zip(sdf) {
+            assert_eq!(&rgba[..3], rgb, "character was: {character}");
+            assert_eq!(rgba[3], d, "character was: {character}");
+        }
+    }
+}
+
+#[test]
+fn dual_sdf_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+
+    for character in ['#', 'A', 'g', '@'] {
+        let (metrics, dual) = font.rasterize_dual_sdf(character, 64.0);
+        let (sdf_metrics, sdf) = font.rasterize_sdf(character, 64.0);
+        assert_eq!(metrics, sdf_metrics, "character was: {character}");
+        assert_eq!(dual.len(), sdf.len() * 2, "character was: {character}");
+
+        // the glyph is the intersection of both channels
+        let mismatches = dual
+            .chunks_exact(2)
+            .zip(sdf.iter())
+            .filter(|(rg, d)| (rg[0].min(rg[1]) >= 128) != (**d >= 128))
+            .count();
+        assert!(
+            mismatches * 100 < sdf.len(),
+            "character was: {character}, mismatches: {mismatches}"
+        );
+    }
+
+    // with only straight edges both channels are exact inside and on the edges,
+    // outside they only differ past the corners
+    for character in ['-', 'l', 'I', 'H'] {
+        for px in [16.0, 48.0, 100.0] {
+            let (_, dual) = font.rasterize_dual_sdf(character, px);
+            let (_, sdf) = font.rasterize_sdf(character, px);
+
+            for (rg, &d) in dual.chunks_exact(2).zip(sdf.iter()) {
+                let min = rg[0].min(rg[1]);
+                if d >= 128 {
+                    assert_eq!(min, d, "character was: {character}, px: {px}");
+                } else {
+                    assert!(min < 128, "character was: {character}, px: {px}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn curve_distance_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+
+    let curve = Edge::Curve(Curve {
+        from: Vec2::new(0.0, 0.0),
+        by_a: Vec2::new(400.0, 300.0),
+        by_b: Vec2::new(0.0, 300.0),
+        to: Vec2::new(400.0, 0.0),
+    });
+    let (geometry, _) = font.geometry('@');
+
+    for edge in geometry.iter_edges().chain([curve]) {
+        for (x, y) in [
+            (-200.0, 100.0),
+            (300.0, 700.0),
+            (900.0, -50.0),
+            (600.0, 600.0),
+        ] {
+            let p = Vec2::new(x, y);
+            let exact = edge.distance_ord((Vec4::splat(x), Vec4::splat(y))).x.sqrt();
+            let sampled = (0..=10000)
+                .map(|i| i as f32 / 10000.0)
+                .map(|t| match edge {
+                    Edge::Line(line) => line.from.lerp(line.to, t),
+                    Edge::Quad(quad) => quad.point(t),
+                    Edge::Curve(curve) => curve.point(t),
+                })
+                .map(|q| q.distance(p))
+                .fold(f32::INFINITY, f32::min);
+            assert!((exact - sampled).abs() < 0.1, "{edge:?} {p}");
+        }
+    }
+}
+
+#[test]
+fn flatten_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    let (geometry, _) = font.geometry('O');
+
+    let line_count = |px: f32| {
+        geometry
+            .flatten(CURVE_TOLERANCE / font.scale_factor(px))
+            .iter_lines()
+            .count()
+    };
+    assert!(line_count(12.0) < line_count(64.0));
+    assert!(line_count(64.0) < line_count(256.0));
+}
+
+#[test]
+fn settings_test() {
+    const PX: f32 = 32.0;
+
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    let settings = SdfSettings {
+        spread: Spread::Pixels(8.0),
+        edge_value: 0.5,
+        spread_value: 0.5,
+    };
+    assert_eq!(font.radius_with(PX, &settings), 9);
+
+    for character in ['#', 'A', 'g', '@'] {
+        let (a, sdf) = font.rasterize_sdf_with(character, PX, &settings);
+        let b = font.metrics_sdf_with(character, PX, &settings);
+        assert_eq!(a, b, "character was: {character}");
+
+        let simple = font.metrics(character, PX, false);
+        assert_eq!(a.xmin, simple.xmin - 9, "character was: {character}");
+        assert_eq!(a.ymin, simple.ymin - 9, "character was: {character}");
+
+        // the padding is about the spread, so the borders are (almost) black
+        assert!(sdf[..a.width].iter().all(|&v| v <= 32));
+        assert!(sdf.iter().any(|&v| v > 128));
+    }
+}
+
+#[test]
+fn sdf_f32_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    let (metrics, pixels) = font.rasterize_sdf_f32('o', 32.0, DistanceUnit::Pixels);
+    let (_, em) = font.rasterize_sdf_f32('o', 32.0, DistanceUnit::Em);
+    assert_eq!(pixels.len(), metrics.width * metrics.height);
+    assert_eq!(em.len(), pixels.len());
+
+    // the corners are outside and the edge is crossed
+    assert!(pixels[0] < 0.0);
+    assert!(pixels[pixels.len() - 1] < 0.0);
+    assert!(pixels.iter().any(|&d| d > 0.0));
+
+    for (p, e) in pixels.iter().zip(&em) {
+        assert!((p / 32.0 - e).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn sdf_16_bit_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    let (metrics, bytes) = font.rasterize_sdf('g', 48.0);
+    let (metrics_u16, words) = font.rasterize_sdf_u16('g', 48.0);
+    let (metrics_f16, halfs) = font.rasterize_sdf_f16('g', 48.0);
+    assert_eq!(metrics, metrics_u16);
+    assert_eq!(metrics, metrics_f16);
+    assert_eq!(bytes.len(), words.len());
+    assert_eq!(bytes.len(), halfs.len());
+
+    // the same mapping, just more precision
+    for ((&b, &w), &h) in bytes.iter().zip(&words).zip(&halfs) {
+        assert!((w as f32 / 65535.0 * 255.0 - b as f32).abs() <= 1.0);
+        assert!((h.to_f32() * 255.0 - b as f32).abs() <= 1.1);
+    }
+}
+
+#[test]
+fn sdf_into_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    let (metrics, image) = font.rasterize_sdf('&', 40.0);
+
+    // write into the middle of a larger image
+    let stride = metrics.width + 7;
+    let mut dst = vec![1; stride * (metrics.height + 3)];
+    let offset = stride * 2 + 3;
+    let metrics_into = font.rasterize_sdf_into('&', 40.0, &mut dst[offset..], stride);
+    assert_eq!(metrics, metrics_into);
+
+    for (y, row) in dst.chunks(stride).enumerate() {
+        for (x, &pixel) in row.iter().enumerate() {
+            let (gx, gy) = (x.wrapping_sub(3), y.wrapping_sub(2));
+            if gx < metrics.width && gy < metrics.height {
+                assert_eq!(pixel, image[gy * metrics.width + gx]);
+            } else {
+                assert_eq!(pixel, 1);
+            }
+        }
+    }
+}
+
+#[test]
+fn atlas_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    let settings = AtlasSettings {
+        width: 256,
+        height: 256,
+        ..Default::default()
+    };
+    let atlas = Atlas::new(&font, 32.0, (' '..='~').chain(['ä', 'ö']), &settings);
+    assert!(atlas.pages().len() > 1);
+
+    let layers = atlas.texture_array();
+    assert_eq!(layers.len(), atlas.pages().len() * 256 * 256);
+    assert_eq!(layers[256 * 256..2 * 256 * 256], atlas.pages()[1].image);
+
+    for (i, page) in atlas.pages().iter().enumerate() {
+        GrayImage::from_raw(page.width as _, page.height as _, page.image.clone())
+            .unwrap()
+            .save(format!("atlas_test_{i}.png"))
+            .unwrap();
+    }
+
+    let glyphs: Vec<_> = atlas.glyphs().collect();
+    for (i, a) in glyphs.iter().enumerate() {
+        assert_eq!(a.metrics, font.metrics_indexed_sdf(a.index, 32.0));
+        assert!(a.rect.x + a.rect.width <= 256 && a.rect.y + a.rect.height <= 256);
+
+        // no overlaps
+        for b in &glyphs[i + 1..] {
+            assert!(
+                a.page != b.page
+                    || a.rect.width == 0
+                    || b.rect.width == 0
+                    || a.rect.x >= b.rect.x + b.rect.width
+                    || b.rect.x >= a.rect.x + a.rect.width
+                    || a.rect.y >= b.rect.y + b.rect.height
+                    || b.rect.y >= a.rect.y + a.rect.height
+            );
+        }
+    }
+
+    // the packed bitmap is the same as the rasterized one
+    let glyph = atlas.glyph('R').unwrap();
+    let (_, sdf) = font.rasterize_sdf('R', 32.0);
+    let page = &atlas.pages()[glyph.page];
+    for y in 0..glyph.rect.height {
+        let row = (glyph.rect.y + y) * page.width + glyph.rect.x;
+        assert_eq!(
+            page.image[row..row + glyph.rect.width],
+            sdf[y * glyph.rect.width..(y + 1) * glyph.rect.width]
+        );
+    }
+    assert_eq!(
+        glyph.uv_min * 256.0,
+        Vec2::new(glyph.rect.x as f32, glyph.rect.y as f32)
+    );
+}
+
+#[test]
+fn dynamic_atlas_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    let settings = AtlasSettings {
+        width: 128,
+        height: 128,
+        ..Default::default()
+    };
+    let mut atlas = DynamicAtlas::new(24.0, &settings);
+
+    for (i, c) in ('A'..='Z').chain('a'..='z').chain('A'..='Z').enumerate() {
+        let glyph = atlas.glyph(&font, c).unwrap();
+        let dirty = atlas.take_dirty();
+        if i < 52 {
+            // new glyphs are uploaded
+            assert!(dirty.iter().any(|(_, rect)| rect.x == glyph.rect.x
+                && rect.y == glyph.rect.y
+                && rect.width >= glyph.rect.width
+                && rect.height >= glyph.rect.height));
+        }
+
+        // the most recently used glyph is always there and intact
+        let (_, sdf) = font.rasterize_sdf(c, 24.0);
+        let page = &atlas.pages()[glyph.page];
+        for y in 0..glyph.rect.height {
+            let row = (glyph.rect.y + y) * page.width + glyph.rect.x;
+            assert_eq!(
+                page.image[row..row + glyph.rect.width],
+                sdf[y * glyph.rect.width..(y + 1) * glyph.rect.width]
+            );
+        }
+    }
+
+    // had to evict some
+    assert!(atlas.glyphs().count() < 52);
+    assert!(atlas.take_dirty().is_empty());
+    assert_eq!(atlas.pages().len(), 1);
+
+    // or add more pages
+    let mut atlas = DynamicAtlas::new(
+        24.0,
+        &AtlasSettings {
+            max_pages: 4,
+            ..settings
+        },
+    );
+    for c in ('A'..='Z').chain('a'..='z') {
+        atlas.glyph(&font, c).unwrap();
+    }
+    assert_eq!(atlas.glyphs().count(), 52);
+    assert!(atlas.pages().len() > 1);
+    assert!(atlas.take_dirty().iter().any(|(page, _)| *page > 0));
+
+    // too large for the page
+    let mut atlas = DynamicAtlas::new(200.0, &settings);
+    assert_eq!(atlas.glyph(&font, 'W'), None);
+}
+
+#[test]
+fn msdf_atlas_gen_json_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    let settings = AtlasSettings {
+        width: 512,
+        height: 512,
+        sdf: SdfSettings {
+            spread: Spread::Pixels(4.0),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let atlas = Atlas::new(&font, 32.0, ['A', 'V', 'a', ' '], &settings);
+    let json = export::msdf_atlas_gen_json(&atlas, &font);
+
+    assert!(json.starts_with(r#"{"atlas":{"type":"sdf","distanceRange":8,"#));
+    assert!(json.contains(r#""width":512,"height":512,"yOrigin":"bottom"}"#));
+    assert!(json.contains(r#"{"unicode":32,"advance":"#));
+    assert!(json.contains(r#""kerning":[{"unicode1":65,"unicode2":86,"advance":-"#));
+    assert!(json.ends_with("]}"));
+    assert_eq!(json.matches("planeBounds").count(), 3);
+    assert_eq!(json.matches('{').count(), json.matches('}').count());
+
+    let glyph = atlas.glyph('A').unwrap();
+    assert!(json.contains(&format!(
+        r#""atlasBounds":{{"left":{},"bottom":{},"right":{},"top":{}}}"#,
+        glyph.rect.x,
+        512 - glyph.rect.y - glyph.rect.height,
+        glyph.rect.x + glyph.rect.width,
+        512 - glyph.rect.y,
+    )));
+}
+
+#[test]
+fn bmfont_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    let atlas = Atlas::new(&font, 32.0, ('a'..='z').chain([' ']), &Default::default());
+    let fnt = export::bmfont(&atlas, &font, "Roboto", |page| format!("roboto_{page}.png"));
+    let lines: Vec<&str> = fnt.lines().collect();
+
+    assert!(lines[0].starts_with(r#"info face="Roboto" size=32 "#));
+    assert!(lines[1].starts_with("common lineHeight=38 base=30 scaleW=1024 scaleH=1024 pages=1 "));
+    assert_eq!(lines[2], r#"page id=0 file="roboto_0.png""#);
+    assert_eq!(lines[3], "chars count=27");
+    assert!(lines[4].starts_with("char id=32 x=0 y=0 width=0 height=0 "));
+
+    let glyph = atlas.glyph('x').unwrap();
+    let m = &glyph.metrics;
+    assert!(lines.contains(
+        &format!(
+            "char id=120 x={} y={} width={} height={} xoffset={} yoffset={} xadvance={} page=0 chnl=15",
+            glyph.rect.x,
+            glyph.rect.y,
+            m.width,
+            m.height,
+            m.xmin,
+            30 - m.ymin - m.height as i32,
+            m.advance_width.round(),
+        )
+        .as_str()
+    ));
+}
+
+#[test]
+fn baked_atlas_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    let settings = AtlasSettings {
+        width: 256,
+        height: 256,
+        ..Default::default()
+    };
+    let atlas = Atlas::new(&font, 32.0, ' '..='~', &settings);
+    let bin = export::binary(&atlas, &font);
+    let baked = BakedAtlas::parse(&bin).unwrap();
+
+    assert_eq!(baked.px(), 32.0);
+    assert_eq!(baked.distance_range(), atlas.distance_range(&font));
+    assert_eq!(
+        baked.line_metrics(),
+        font.inner().horizontal_line_metrics(32.0)
+    );
+    assert_eq!(baked.page_count(), atlas.pages().len());
+    assert_eq!(baked.texture_array(), atlas.texture_array());
+    assert_eq!(baked.page(1), Some(&atlas.pages()[1].image[..]));
+    assert_eq!(baked.page(atlas.pages().len()), None);
+    assert_eq!(baked.page(usize::MAX), None);
+    assert_eq!(baked.glyphs().count(), atlas.glyphs().count());
+    assert_eq!(baked.chars().count(), 95);
+    for c in ' '..='~' {
+        assert_eq!(baked.glyph(c).as_ref(), atlas.glyph(c));
+    }
+    assert_eq!(baked.glyph('ä'), None);
+
+    assert!(BakedAtlas::parse(&bin[..bin.len() - 1]).is_err());
+    assert!(BakedAtlas::parse(b"not an atlas").is_err());
+}
+
+#[test]
+fn layout_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    let atlas = Atlas::new(&font, 32.0, ' '..='~', &Default::default());
+
+    let quads = layout::layout(&font, &atlas, "hello world", 64.0, None);
+    assert_eq!(quads.len(), 10);
+    assert!(quads.windows(2).all(|q| q[0].min.x < q[1].min.x));
+    assert!(
+        quads
+            .iter()
+            .all(|q| q.min.y == quads[0].min.y || q.index != quads[0].index)
+    );
+
+    // twice the bake size
+    let glyph = atlas.glyph('h').unwrap();
+    let size = quads[0].max - quads[0].min;
+    assert_eq!(
+        size,
+        2.0 * Vec2::new(glyph.rect.width as f32, glyph.rect.height as f32)
+    );
+    assert_eq!(
+        (quads[0].uv_min, quads[0].uv_max),
+        (glyph.uv_min, glyph.uv_max)
+    );
+
+    // 'world' wraps to the next line
+    let wrapped = layout::layout(&font, &atlas, "hello world", 64.0, Some(200.0));
+    assert_eq!(wrapped.len(), 10);
+    assert_eq!(wrapped[5].byte_offset, 6);
+    assert_eq!(&wrapped[..5], &quads[..5]);
+    let line_height = font.horizontal_line_metrics(64.0).unwrap().new_line_size;
+    let world = layout::layout(&font, &atlas, "world", 64.0, None);
+    for (wrapped, world) in wrapped[5..].iter().zip(&world) {
+        let offset = Vec2::new(0.0, line_height);
+        assert_eq!(wrapped.index, world.index);
+        assert!(wrapped.min.abs_diff_eq(world.min + offset, 1e-3));
+        assert!(wrapped.max.abs_diff_eq(world.max + offset, 1e-3));
+    }
+    // only the sdf padding can go past the max width
+    let padding = font.radius_with(atlas.px(), &atlas.settings().sdf) as f32 * 2.0;
+    assert!(wrapped.iter().all(|q| q.max.x <= 200.0 + padding));
+
+    // explicit newlines
+    let lines = layout::layout(&font, &atlas, "a\na", 64.0, None);
+    assert_eq!(lines[0].min.x, lines[1].min.x);
+    assert!(lines[1].min.y > lines[0].min.y);
+}
+
+#[test]
+fn mesh_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    let atlas = Atlas::new(&font, 32.0, ' '..='~', &Default::default());
+    let quads = layout::layout(&font, &atlas, "mesh", 32.0, None);
+
+    let layout = [
+        VertexAttribute::Position,
+        VertexAttribute::Page,
+        VertexAttribute::ColorU8,
+        VertexAttribute::Params,
+    ];
+    let mut builder = MeshBuilder::new(&layout, IndexFormat::U32);
+    builder.push_quads(&quads, [1.0, 0.5, 0.0, 1.0], [0.1, 0.2, 0.3, 0.4]);
+    let mesh = builder.build();
+
+    assert_eq!(mesh.stride, 8 + 4 + 4 + 16);
+    assert_eq!(mesh.vertices.len(), 4 * 4 * mesh.stride);
+    assert_eq!(
+        mesh.indices,
+        Indices::U32(
+            (0..4)
+                .flat_map(|q| [0, 1, 2, 0, 2, 3].map(|i| q * 4 + i))
+                .collect()
+        )
+    );
+
+    // the bottom right corner of the second quad
+    let vertex = &mesh.vertices[6 * mesh.stride..7 * mesh.stride];
+    let float = |i: usize| f32::from_ne_bytes(vertex[i..i + 4].try_into().unwrap());
+    assert_eq!((float(0), float(4)), (quads[1].max.x, quads[1].max.y));
+    assert_eq!(float(8), 0.0);
+    assert_eq!(vertex[12..16], [255, 128, 0, 255]);
+    assert_eq!(float(28), 0.4);
+
+    let mesh = MeshBuilder::default().build();
+    assert_eq!(mesh.stride, 32);
+    assert!(mesh.indices.is_empty());
+
+    // a quad past the u16 limit panics without pushing anything
+    let mut builder = MeshBuilder::new(&layout, IndexFormat::U16);
+    for _ in 0..16384 / quads.len() {
+        builder.push_quads(&quads, [1.0; 4], [0.0; 4]);
+    }
+    let overflow = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        builder.push_quad(&quads[0], [1.0; 4], [0.0; 4])
+    }));
+    assert!(overflow.is_err());
+    let mesh = builder.build();
+    assert_eq!(mesh.vertices.len(), 16384 * 4 * mesh.stride);
+    assert_eq!(mesh.indices.len(), 16384 * 6);
+}
+
+#[test]
+fn kerning_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+
+    // from the GPOS table
+    let kern = font.horizontal_kern('A', 'V', 64.0).unwrap();
+    assert!(kern < 0.0);
+    assert_eq!(font.horizontal_kern('A', 'V', 32.0), Some(kern / 2.0));
+    assert_eq!(font.horizontal_kern('x', 'x', 64.0), None);
+
+    let advance = font.horizontal_advance_indexed(font.lookup_glyph_index('A'), 64.0);
+    assert_eq!(advance, font.metrics_sdf('A', 64.0).advance_width);
+
+    let line = font.horizontal_line_metrics(64.0).unwrap();
+    assert_eq!(
+        line.new_line_size,
+        line.ascent - line.descent + line.line_gap
+    );
+}
+
+#[test]
+fn collection_test() {
+    // a collection of two copies of the same font
+    let header_len = 12 + 4 * 2;
+    let mut ttc = Vec::new();
+    ttc.extend_from_slice(b"ttcf");
+    ttc.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    ttc.extend_from_slice(&2u32.to_be_bytes());
+    ttc.extend_from_slice(&(header_len as u32).to_be_bytes());
+    ttc.extend_from_slice(&(header_len as u32).to_be_bytes());
+
+    // table offsets are from the start of the file
+    let mut font = FONT_FILE.to_vec();
+    let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+    for i in 0..num_tables {
+        let at = 12 + 16 * i + 8;
+        let offset = u32::from_be_bytes(font[at..at + 4].try_into().unwrap());
+        font[at..at + 4].copy_from_slice(&(offset + header_len as u32).to_be_bytes());
+    }
+    ttc.extend_from_slice(&font);
+
+    let settings = |collection_index| FontSettings {
+        collection_index,
+        ..Default::default()
+    };
+    let second = Font::from_bytes_with(&ttc, settings(1)).unwrap();
+    let single = Font::from_bytes(FONT_FILE).unwrap();
+    assert_eq!(
+        second.rasterize_sdf('@', 32.0),
+        single.rasterize_sdf('@', 32.0)
+    );
+    assert_eq!(
+        second.horizontal_kern('A', 'V', 32.0),
+        single.horizontal_kern('A', 'V', 32.0)
+    );
+    assert!(Font::from_bytes_with(&ttc, settings(2)).is_err());
+}
+
+#[test]
+fn error_test() {
+    assert!(matches!(
+        Font::from_bytes(b"not a font"),
+        Err(FontSdfError::Parse(_))
+    ));
+
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    let out_of_range = font.inner().glyph_count();
+    assert_eq!(
+        font.try_rasterize_indexed_sdf(out_of_range, 32.0),
+        Err(FontSdfError::GlyphIndex(out_of_range))
+    );
+    assert_eq!(
+        font.try_metrics_indexed_sdf(u16::MAX, 32.0),
+        Err(FontSdfError::GlyphIndex(u16::MAX))
+    );
+
+    let index = font.lookup_glyph_index('e');
+    assert_eq!(
+        font.try_rasterize_indexed_sdf(index, 32.0),
+        Ok(font.rasterize_indexed_sdf(index, 32.0))
+    );
+    let space = font.lookup_glyph_index(' ');
+    assert!(font.try_metrics_indexed_sdf(space, 32.0).is_ok());
+
+    assert_eq!(font.horizontal_advance_indexed(out_of_range, 32.0), 0.0);
+    assert!(font.try_rasterize_indexed_msdf(out_of_range, 32.0).is_err());
+    assert!(
+        font.try_rasterize_indexed_sdf_f32(out_of_range, 32.0, DistanceUnit::Pixels)
+            .is_err()
+    );
+
+    let metrics = font.metrics_indexed_sdf(index, 32.0);
+    let mut image = vec![0; metrics.width * metrics.height];
+    assert!(matches!(
+        font.try_rasterize_indexed_sdf_into(index, 32.0, &mut image, metrics.width - 1),
+        Err(FontSdfError::Destination(_))
+    ));
+    assert!(matches!(
+        font.try_rasterize_indexed_sdf_into(index, 32.0, &mut image[1..], metrics.width),
+        Err(FontSdfError::Destination(_))
+    ));
+    assert_eq!(
+        font.try_rasterize_indexed_sdf_into(index, 32.0, &mut image, metrics.width),
+        Ok(metrics)
+    );
+}
+
+#[test]
+fn variation_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+
+    // roboto isn't variable
+    assert!(font.variation_axes().is_empty());
+    assert!(font.named_instances().is_empty());
+    let wght = Tag::from_bytes(b"wght");
+    assert_eq!(
+        font.instance(&[(wght, 700.0)]).unwrap_err(),
+        FontSdfError::UnknownAxis(wght)
+    );
+
+    // the default instance
+    let instance = font.instance(&[]).unwrap();
+    assert!(instance.variations().is_empty());
+    assert_eq!(
+        instance.rasterize_sdf('g', 32.0),
+        font.rasterize_sdf('g', 32.0)
+    );
+
+    let font = Font::from_bytes(TEST_FONT_FILE).unwrap();
+    let axes = font.variation_axes();
+    assert_eq!(axes.len(), 1);
+    assert_eq!(axes[0].tag, wght);
+    assert_eq!(
+        (axes[0].min_value, axes[0].def_value, axes[0].max_value),
+        (100.0, 400.0, 900.0)
+    );
+    assert_eq!(
+        font.named_instances(),
+        [
+            NamedInstance {
+                name: Some("Thin".into()),
+                coordinates: vec![(wght, 100.0)],
+            },
+            NamedInstance {
+                name: Some("Black".into()),
+                coordinates: vec![(wght, 900.0)],
+            },
+        ]
+    );
+
+    // the square is 400 units wider at 900 than at 100
+    let instances = font.named_instances();
+    let thin = font.instance(&instances[0].coordinates).unwrap();
+    let black = font.instance(&instances[1].coordinates).unwrap();
+    let thin_metrics = thin.metrics_sdf('\u{e000}', 100.0);
+    let black_metrics = black.metrics_sdf('\u{e000}', 100.0);
+    assert_eq!(thin_metrics.bounds.width, 30.0);
+    assert_eq!(black_metrics.bounds.width, 70.0);
+    assert_eq!(thin_metrics.advance_width, 50.0);
+    assert_eq!(black_metrics.advance_width, 90.0);
+    assert_eq!(thin.horizontal_advance_indexed(1, 100.0), 50.0);
+    assert_ne!(
+        thin.rasterize_sdf('\u{e000}', 32.0),
+        black.rasterize_sdf('\u{e000}', 32.0)
+    );
+
+    // the triangle doesn't vary
+    assert_eq!(
+        thin.rasterize_sdf('A', 32.0),
+        black.rasterize_sdf('A', 32.0)
+    );
+}
+
+#[test]
+fn lazy_outline_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    assert_eq!(font.outlined_glyph_count(), 0);
+
+    // outlined once and then cached
+    let first = font.geometry('g');
+    let second = font.geometry('g');
+    assert!(core::ptr::eq(first, second));
+    assert!(first.1.width() > 0);
+    assert_eq!(font.outlined_glyph_count(), 1);
+
+    // the chars and metrics don't need the outlines
+    let index = font.lookup_glyph_index('x');
+    assert_ne!(index, 0);
+    assert!(font.horizontal_advance_indexed(index, 32.0) > 0.0);
+    assert!(font.horizontal_kern('A', 'V', 32.0).is_some());
+    assert!(font.horizontal_line_metrics(32.0).is_some());
+    assert_eq!(font.outlined_glyph_count(), 1);
+
+    let (_, sdf) = font.rasterize_sdf('g', 32.0);
+    let (_, again) = font.clone().rasterize_sdf('g', 32.0);
+    assert_eq!(sdf, again);
+    font.rasterize_sdf('i', 32.0);
+    assert_eq!(font.outlined_glyph_count(), 2);
+    assert!(font.glyph_count() > 1000);
+
+    // fontdue is loaded separately, only when it is used
+    let mut font = font;
+    assert_eq!(font.chars(), font.inner().chars());
+    assert_eq!(
+        font.horizontal_line_metrics(32.0),
+        font.inner().horizontal_line_metrics(32.0)
+    );
+    assert_eq!(font.inner_mut().glyph_count(), font.glyph_count());
+    assert_eq!(
+        font.rasterize('g', 32.0, false),
+        font.inner().rasterize('g', 32.0)
+    );
+}
+
+#[test]
+fn unmapped_glyph_test() {
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+
+    // ligatures, small caps and other glyphs only reachable through gsub
+    let mapped: Vec<u16> = font.chars().values().map(|i| i.get()).collect();
+    let unmapped: Vec<u16> = (1..font.glyph_count())
+        .filter(|i| !mapped.contains(i))
+        .filter(|i| font.geometry_indexed(*i).1.width() > 0)
+        .collect();
+    assert!(!unmapped.is_empty());
+
+    for index in unmapped.into_iter().take(20) {
+        let (metrics, sdf) = font.try_rasterize_indexed_sdf(index, 24.0).unwrap();
+        assert!(metrics.width > 0 && metrics.height > 0);
+        assert!(sdf.iter().any(|v| *v > 127));
+    }
+}
+
+#[cfg(feature = "shaping")]
+#[test]
+fn shape_test() {
+    use fontsdf::shape::{self, Feature, UnicodeBuffer};
+
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+
+    let glyphs = shape::shape(&font, "fi", 32.0);
+    assert_eq!(glyphs.len(), 1);
+    let index = glyphs[0].index;
+    assert_ne!(index, font.lookup_glyph_index('f'));
+    let (metrics, sdf) = font.rasterize_indexed_sdf(index, 32.0);
+    assert!(metrics.width > 0 && sdf.iter().any(|v| *v > 127));
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str("fi");
+    buffer.guess_segment_properties();
+    let no_liga = "-liga".parse::<Feature>().unwrap();
+    let glyphs = shape::shape_with(&font, buffer, 32.0, &[no_liga]);
+    assert_eq!(glyphs.len(), 2);
+    assert_eq!(glyphs[1].byte_offset, 1);
+
+    // gpos kerning matches the font
+    let glyphs = shape::shape(&font, "AV", 32.0);
+    let kern = font.horizontal_kern('A', 'V', 32.0).unwrap();
+    let advance = font.metrics_sdf('A', 32.0).advance_width;
+    assert!((glyphs[1].position.x - (advance + kern)).abs() < 0.01);
+
+    let indices = glyphs.iter().map(|glyph| glyph.index);
+    let atlas = Atlas::from_indices(&font, 32.0, indices, &AtlasSettings::default());
+    let quads = shape::quads(&font, &atlas, &glyphs, 32.0, Vec2::ZERO);
+    assert_eq!(quads.len(), 2);
+}
+
+#[test]
+fn font_set_test() {
+    const PX: f32 = 32.0;
+
+    let font = Font::from_bytes(FONT_FILE).unwrap();
+    // 1000 units per em instead of 2048, with a square at U+E000
+    let fallback = Font::from_bytes(TEST_FONT_FILE).unwrap();
+    let set = FontSet::new(font.clone()).with(fallback.clone());
+
+    // both have 'A', the primary font comes first
+    assert_eq!(set.lookup('A'), (0, font.lookup_glyph_index('A')));
+    assert_eq!(set.lookup('\u{e000}'), (1, 1));
+    // missing everywhere, the primary .notdef
+    assert_eq!(set.lookup('\u{4e2d}'), (0, 0));
+
+    assert_eq!(set.rasterize_sdf('a', PX), font.rasterize_sdf('a', PX));
+    // with the spread converted to the fallback
+    let default = set.settings_for(1, &SdfSettings::default());
+    assert_ne!(default, SdfSettings::default());
+    assert_eq!(
+        set.rasterize_sdf('\u{e000}', PX),
+        fallback.rasterize_sdf_with('\u{e000}', PX, &default)
+    );
+
+    // px is the em size in both fonts
+    let sf = fallback.scale_factor(PX);
+    assert_eq!(sf, PX / 1000.0);
+    let metrics = set.metrics_sdf('\u{e000}', PX);
+    assert_eq!(metrics.bounds.width, 500.0 * sf);
+    assert_eq!(metrics.advance_width, 700.0 * sf);
+    assert_eq!(set.horizontal_advance('\u{e000}', PX), 700.0 * sf);
+
+    assert_eq!(
+        set.horizontal_kern('A', 'V', PX),
+        font.horizontal_kern('A', 'V', PX)
+    );
+    assert_eq!(set.horizontal_kern('A', '\u{e000}', PX), None);
+
+    // the deepest descent and the tallest ascent, from either font
+    let roboto = font.horizontal_line_metrics(PX).unwrap();
+    let line_metrics = set.horizontal_line_metrics(PX).unwrap();
+    assert!((line_metrics.ascent - roboto.ascent.max(800.0 * sf)).abs() < 1e-4);
+    assert!((line_metrics.descent - roboto.descent.min(-200.0 * sf)).abs() < 1e-4);
+
+    // font units of the primary font for all fallbacks
+    let settings = SdfSettings {
+        spread: Spread::FontUnits(256.0),
+        ..SdfSettings::default()
+    };
+    assert_eq!(set.settings_for(0, &settings), settings);
+    let fallback_settings = set.settings_for(1, &settings);
+    assert_eq!(fallback_settings.spread, Spread::Em(256.0 / 2048.0));
+    assert_eq!(
+        fallback.radius_with(PX, &fallback_settings),
+        font.radius_with(PX, &settings)
+    );
+    // and not the font units of the fallback
+    assert_ne!(
+        fallback.radius_with(PX, &settings),
+        font.radius_with(PX, &settings)
+    );
+    assert_eq!(
+        set.metrics_sdf_with('\u{e000}', PX, &settings),
+        fallback.metrics_sdf_with('\u{e000}', PX, &fallback_settings)
+    );
+}